@@ -0,0 +1,184 @@
+use crate::{CompactionPolicy, ConcurrentDb, Config, Db, DbError, DbResult, Snapshot};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, RwLockReadGuard};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task;
+
+type Commit = Box<dyn FnOnce(&DbResult<()>) + Send>;
+/// Runs a transaction, or reports why its group couldn't start.
+type Job<D> = Box<dyn FnOnce(Result<&mut D, &DbError>) -> Commit + Send>;
+
+enum Message<D> {
+    Job(Job<D>),
+    /// stop once the jobs sent before this are committed, see [AsyncDb::close]
+    Close,
+}
+
+/// An async handle to a [Db], available with the `tokio` feature.
+///
+/// Transactions are handed to a dedicated writer task. Transactions that arrive while the writer
+/// is busy are grouped into a single db transaction, so the whole group is written to the log at
+/// once. Each transaction's future resolves after its group has been committed.
+///
+/// Like [ConcurrentDb::transaction], a transaction that returns an error is still committed along
+/// with the rest of its group, as db-rs cannot roll back writes. So is one that panics, whose
+/// future resolves to an error while the writer carries on with the next group.
+///
+/// The writer holds on to the db until every handle is dropped, [AsyncDb::close] stops it right
+/// away, which releases the log once the other handles are dropped too.
+pub struct AsyncDb<D> {
+    db: ConcurrentDb<D>,
+    writer: mpsc::UnboundedSender<Message<D>>,
+    task: Arc<Mutex<Option<task::JoinHandle<()>>>>,
+}
+
+impl<D> Clone for AsyncDb<D> {
+    fn clone(&self) -> Self {
+        Self { db: self.db.clone(), writer: self.writer.clone(), task: self.task.clone() }
+    }
+}
+
+impl<D> AsyncDb<D>
+where
+    D: Db + Send + Sync + 'static,
+{
+    /// Initializes the db on a blocking thread, must be called from within a tokio runtime.
+    pub async fn init(config: Config) -> DbResult<Self> {
+        let db = task::spawn_blocking(move || D::init(config))
+            .await
+            .map_err(|_| DbError::InitPanicked)??;
+        Ok(Self::new(db))
+    }
+
+    /// Starts the writer task for `db`, must be called from within a tokio runtime.
+    pub fn new(db: D) -> Self {
+        let db = ConcurrentDb::new(db);
+        let (writer, jobs) = mpsc::unbounded_channel();
+
+        let writer_db = db.clone();
+        let task = task::spawn_blocking(move || Self::write_loop(writer_db, jobs));
+
+        Self { db, writer, task: Arc::new(Mutex::new(Some(task))) }
+    }
+
+    /// Commit the transactions that were already sent, stop the writer and drop this handle's
+    /// db. Transactions sent through other handles afterwards fail with [DbError::WriterGone].
+    pub async fn close(self) -> DbResult<()> {
+        let Self { db, writer, task } = self;
+        // the writer may already be gone, which is what closing is after
+        let _ = writer.send(Message::Close);
+        let task = task.lock()?.take();
+        if let Some(task) = task {
+            task.await.map_err(|_| DbError::WriterGone)?;
+        }
+        drop(db);
+        Ok(())
+    }
+
+    /// Shared access to the tables. This only waits for the writer to finish applying a group of
+    /// transactions in memory, which is short, so it isn't async.
+    pub fn read(&self) -> DbResult<RwLockReadGuard<'_, D>> {
+        self.db.read()
+    }
+
+    pub fn snapshot(&self) -> DbResult<Snapshot<D>> {
+        self.db.snapshot()
+    }
+
+    /// The underlying [ConcurrentDb], writes made through it bypass group commit.
+    pub fn concurrent(&self) -> &ConcurrentDb<D> {
+        &self.db
+    }
+
+    /// Run `f` on the writer task. Single writes are just small transactions:
+    /// `db.transaction(|db| db.table.insert(k, v)).await`.
+    pub async fn transaction<F, R>(&self, f: F) -> DbResult<R>
+    where
+        F: FnOnce(&mut D) -> DbResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (done, result) = oneshot::channel();
+        let job: Job<D> = Box::new(move |db| {
+            let result = match db {
+                Ok(db) => panic::catch_unwind(AssertUnwindSafe(|| f(db)))
+                    .unwrap_or(Err(DbError::TxPanicked)),
+                Err(err) => {
+                    let _ = done.send(Err(err.duplicate()));
+                    return Box::new(|_| {});
+                }
+            };
+            Box::new(move |commit| {
+                let result = match commit {
+                    Ok(()) => result,
+                    Err(err) => Err(err.duplicate()),
+                };
+                // the caller may have stopped waiting
+                let _ = done.send(result);
+            })
+        });
+
+        self.writer
+            .send(Message::Job(job))
+            .map_err(|_| DbError::WriterGone)?;
+
+        result.await.map_err(|_| DbError::WriterGone)?
+    }
+
+    /// Compact the log from a [Snapshot] on a blocking thread.
+    pub async fn compact_log(&self) -> DbResult<()> {
+        let snapshot = self.snapshot()?;
+        task::spawn_blocking(move || snapshot.compact_log())
+            .await
+            .map_err(|_| DbError::CompacterPanicked)?
+    }
+
+    /// Compact the log if `policy` says it's time to, returns whether a compaction happened.
+    pub async fn compact_if(&self, policy: &CompactionPolicy) -> DbResult<bool> {
+        if !policy.should_compact(&self.read()?.log_stats()?) {
+            return Ok(false);
+        }
+        self.compact_log().await?;
+        Ok(true)
+    }
+
+    fn write_loop(db: ConcurrentDb<D>, mut jobs: mpsc::UnboundedReceiver<Message<D>>) {
+        let mut closing = false;
+        while !closing {
+            let mut group = vec![];
+            match jobs.blocking_recv() {
+                Some(Message::Job(job)) => group.push(job),
+                Some(Message::Close) | None => return,
+            }
+            while let Ok(message) = jobs.try_recv() {
+                match message {
+                    Message::Job(job) => group.push(job),
+                    Message::Close => {
+                        closing = true;
+                        break;
+                    }
+                }
+            }
+
+            let mut commits = Vec::with_capacity(group.len());
+            let mut group = group.into_iter();
+            let result = db.write().and_then(|mut db| {
+                let tx = db.begin_transaction()?;
+                for job in group.by_ref() {
+                    commits.push(job(Ok(&mut *db)));
+                }
+                tx.drop_safely()
+            });
+
+            // the jobs that didn't run because the group couldn't start
+            if let Err(err) = &result {
+                for job in group {
+                    commits.push(job(Err(err)));
+                }
+            }
+
+            for commit in commits {
+                commit(&result);
+            }
+        }
+    }
+}
@@ -1,54 +1,362 @@
-use crate::{Db, DbResult};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use crate::{ConcurrentDb, Db, DbError, DbResult, LogStats};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::Duration;
 
+/// Signals a background compacter to stop. Compacters wake up as soon as this is canceled rather
+/// than at their next scheduled compaction.
 #[derive(Default, Clone)]
-pub struct CancelSig(Arc<AtomicBool>);
+pub struct CancelSig(Arc<Signal>);
+
+#[derive(Default)]
+struct Signal {
+    state: Mutex<SignalState>,
+    wake: Condvar,
+    #[cfg(feature = "tokio")]
+    notify: tokio::sync::Notify,
+}
+
+#[derive(Default)]
+struct SignalState {
+    canceled: bool,
+    compact_now: bool,
+}
+
+enum Wake {
+    Canceled,
+    CompactNow,
+    Elapsed,
+}
 
 impl CancelSig {
     pub fn cancel(&self) {
-        self.0.store(true, Ordering::Relaxed);
+        self.state().canceled = true;
+        self.notify();
     }
 
     pub fn is_canceled(&self) -> bool {
-        self.0.load(Ordering::Relaxed)
+        self.state().canceled
+    }
+
+    fn compact_now(&self) {
+        self.state().compact_now = true;
+        self.notify();
+    }
+
+    fn notify(&self) {
+        self.0.wake.notify_all();
+        #[cfg(feature = "tokio")]
+        self.0.notify.notify_waiters();
+    }
+
+    // the state is only ever a couple of flags, so it's still meaningful after a panic
+    fn state(&self) -> MutexGuard<'_, SignalState> {
+        self.0.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn take_wake(state: &mut SignalState) -> Option<Wake> {
+        if state.canceled {
+            Some(Wake::Canceled)
+        } else if state.compact_now {
+            state.compact_now = false;
+            Some(Wake::CompactNow)
+        } else {
+            None
+        }
+    }
+
+    /// Sleep for `timeout`, returning early if canceled or asked to compact.
+    fn sleep(&self, timeout: Duration) -> Wake {
+        let (mut state, _) = self
+            .0
+            .wake
+            .wait_timeout_while(self.state(), timeout, |state| {
+                !state.canceled && !state.compact_now
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        Self::take_wake(&mut state).unwrap_or(Wake::Elapsed)
+    }
+}
+
+/// Decides when the log is worth compacting, based on its [LogStats]. Compaction happens once any
+/// of the thresholds that are set is reached, a policy without thresholds compacts whenever the
+/// log changed since it was last compacted. `min_interval` applies on top of the thresholds.
+///
+/// Use it inline with [Db::compact_if], or from a thread with
+/// [BackgroundCompacter::begin_policy_compacter].
+#[derive(Clone, Debug, Default)]
+pub struct CompactionPolicy {
+    /// compact once the log is at least this many bytes
+    pub max_log_size: Option<u64>,
+
+    /// compact once the log is this many times larger than its live data, see
+    /// [LogStats::compacted_size]
+    pub max_growth_ratio: Option<f64>,
+
+    /// compact once this many writes happened since the last compaction
+    pub max_writes: Option<u64>,
+
+    /// never compact if the last compaction was more recent than this
+    pub min_interval: Option<Duration>,
+}
+
+impl CompactionPolicy {
+    pub fn should_compact(&self, stats: &LogStats) -> bool {
+        // compacting a log that didn't change since it was compacted wouldn't shrink it
+        if stats.writes_since_compaction == 0 && stats.log_size <= stats.compacted_size {
+            return false;
+        }
+
+        if let Some(min_interval) = self.min_interval {
+            if stats.since_compaction < min_interval {
+                return false;
+            }
+        }
+
+        let thresholds = [
+            self.max_log_size.map(|max| stats.log_size >= max),
+            self.max_growth_ratio
+                .map(|ratio| stats.log_size as f64 >= stats.compacted_size as f64 * ratio),
+            self.max_writes
+                .map(|max| stats.writes_since_compaction >= max),
+        ];
+
+        thresholds.iter().all(Option::is_none) || thresholds.contains(&Some(true))
+    }
+}
+
+/// Configures a compacter started with [BackgroundCompacter::spawn_compacter], use
+/// [CompacterConfig::every] as a starting point.
+#[derive(Clone, Debug)]
+pub struct CompacterConfig {
+    /// how often the compacter checks whether it should compact
+    pub freq: Duration,
+
+    /// decides whether the log is worth compacting. Default: compact whenever the log changed
+    pub policy: CompactionPolicy,
+
+    /// how many consecutive failed compactions are retried before the compacter stops. Errors
+    /// caused by a poisoned mutex are never retried. Default: 3
+    pub max_retries: u32,
+
+    /// how long to wait before retrying a failed compaction, doubled after every consecutive
+    /// failure and capped at `freq`. Default: 1s
+    pub retry_backoff: Duration,
+}
+
+impl CompacterConfig {
+    pub fn every(freq: Duration) -> Self {
+        Self {
+            freq,
+            policy: CompactionPolicy::default(),
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A running background compacter, see [BackgroundCompacter::spawn_compacter].
+pub struct CompacterHandle {
+    signal: CancelSig,
+    results: Receiver<DbResult<()>>,
+    thread: JoinHandle<DbResult<usize>>,
+}
+
+impl CompacterHandle {
+    /// Compact as soon as possible, regardless of the compacter's policy.
+    pub fn compact_now(&self) {
+        self.signal.compact_now();
+    }
+
+    /// Stop the compacter, takes effect immediately unless a compaction is in progress.
+    pub fn cancel(&self) {
+        self.signal.cancel();
+    }
+
+    /// The outcome of every compaction the compacter attempted, including ones that will be
+    /// retried.
+    pub fn results(&self) -> &Receiver<DbResult<()>> {
+        &self.results
+    }
+
+    /// Wait for the compacter to stop, returns the number of times compaction took place, or the
+    /// error that stopped it.
+    pub fn join(self) -> DbResult<usize> {
+        self.thread.join().map_err(|_| DbError::CompacterPanicked)?
+    }
+
+    fn spawn<F>(config: CompacterConfig, signal: CancelSig, compact: F) -> Self
+    where
+        F: FnMut(&CompactionPolicy, bool) -> DbResult<bool> + Send + 'static,
+    {
+        let (sender, results) = mpsc::channel();
+        let thread_signal = signal.clone();
+        let thread = thread::spawn(move || Self::run(config, thread_signal, sender, compact));
+        Self { signal, results, thread }
+    }
+
+    fn run<F>(
+        config: CompacterConfig, signal: CancelSig, results: Sender<DbResult<()>>, mut compact: F,
+    ) -> DbResult<usize>
+    where
+        F: FnMut(&CompactionPolicy, bool) -> DbResult<bool>,
+    {
+        let mut count = 0;
+        let mut failures = 0;
+        let mut wait = config.freq;
+        loop {
+            // retries and requested compactions don't wait on the policy
+            let force = match signal.sleep(wait) {
+                Wake::Canceled => return Ok(count),
+                Wake::CompactNow => true,
+                Wake::Elapsed => failures > 0,
+            };
+            wait = config.freq;
+
+            // nobody listening for results isn't a reason to stop compacting
+            match compact(&config.policy, force) {
+                Ok(false) => {}
+                Ok(true) => {
+                    count += 1;
+                    failures = 0;
+                    let _ = results.send(Ok(()));
+                }
+                Err(err) => {
+                    let _ = results.send(Err(err.duplicate()));
+                    failures += 1;
+                    if matches!(err, DbError::MutexPoisoned) || failures > config.max_retries {
+                        return Err(err);
+                    }
+                    wait = config
+                        .retry_backoff
+                        .saturating_mul(1 << (failures - 1).min(16))
+                        .min(config.freq);
+                }
+            }
+        }
     }
 }
 
 pub trait BackgroundCompacter {
     /// Periodically compact the database log in a separate thread
-    /// You can call this function if your db is wrapped in an `Arc<Mutex>`
+    /// You can call this function if your db is wrapped in an `Arc<Mutex>` or a [ConcurrentDb]
     ///
     /// freq determines how often the background thread will aquire a mutex
-    /// and call compact_log() on your db
+    /// and take a [crate::Snapshot] of your db, the log is then compacted from the snapshot without
+    /// holding the mutex
     ///
-    /// cancel is an AtomicBool which can be passed in and signal that compaction
-    /// should cease (could take up-to freq to return)
+    /// cancel is a [CancelSig] which can be passed in and signal that compaction should cease
     ///
     /// this fn returns the number of times compaction took place
-    fn begin_compacter(&self, freq: Duration, cancel: CancelSig) -> JoinHandle<DbResult<usize>>;
+    fn begin_compacter(&self, freq: Duration, cancel: CancelSig) -> JoinHandle<DbResult<usize>> {
+        self.begin_policy_compacter(freq, CompactionPolicy::default(), cancel)
+    }
+
+    /// Like [BackgroundCompacter::begin_compacter], but every freq the background thread only
+    /// compacts if `policy` says the log is worth compacting.
+    fn begin_policy_compacter(
+        &self, freq: Duration, policy: CompactionPolicy, cancel: CancelSig,
+    ) -> JoinHandle<DbResult<usize>> {
+        let config = CompacterConfig { policy, ..CompacterConfig::every(freq) };
+        self.spawn_compacter_with(config, cancel).thread
+    }
+
+    /// Start a background compacter whose results can be observed, and which can be asked to
+    /// compact immediately, through the returned handle.
+    fn spawn_compacter(&self, config: CompacterConfig) -> CompacterHandle {
+        self.spawn_compacter_with(config, CancelSig::default())
+    }
+
+    /// Like [BackgroundCompacter::spawn_compacter], but also stops when `cancel` is canceled.
+    fn spawn_compacter_with(&self, config: CompacterConfig, cancel: CancelSig) -> CompacterHandle;
 }
 
 impl<D> BackgroundCompacter for Arc<Mutex<D>>
 where
     D: Db + Send + Sync + 'static,
 {
-    fn begin_compacter(&self, freq: Duration, cancel: CancelSig) -> JoinHandle<DbResult<usize>> {
+    fn spawn_compacter_with(&self, config: CompacterConfig, cancel: CancelSig) -> CompacterHandle {
+        let db = self.clone();
+        CompacterHandle::spawn(config, cancel, move |policy, force| {
+            let snapshot = {
+                let db = db.lock()?;
+                if !force && !policy.should_compact(&db.log_stats()?) {
+                    return Ok(false);
+                }
+                db.snapshot()?
+            };
+            snapshot.compact_log()?;
+            Ok(true)
+        })
+    }
+}
+
+impl<D> BackgroundCompacter for ConcurrentDb<D>
+where
+    D: Db + Send + Sync + 'static,
+{
+    fn spawn_compacter_with(&self, config: CompacterConfig, cancel: CancelSig) -> CompacterHandle {
         let db = self.clone();
-        thread::spawn(move || {
+        CompacterHandle::spawn(config, cancel, move |policy, force| {
+            if force {
+                db.compact_log()?;
+                Ok(true)
+            } else {
+                db.compact_if(policy)
+            }
+        })
+    }
+}
+
+/// The async counterpart of [BackgroundCompacter], available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub trait AsyncBackgroundCompacter {
+    /// Periodically compact the database log from a tokio task, driven by a tokio interval.
+    ///
+    /// cancel is a [CancelSig] which can be passed in and signal that compaction should cease
+    ///
+    /// this fn returns the number of times compaction took place
+    fn begin_compacter(
+        &self, freq: Duration, cancel: CancelSig,
+    ) -> tokio::task::JoinHandle<DbResult<usize>> {
+        self.begin_policy_compacter(freq, CompactionPolicy::default(), cancel)
+    }
+
+    /// Like [AsyncBackgroundCompacter::begin_compacter], but every tick only compacts if `policy`
+    /// says the log is worth compacting.
+    fn begin_policy_compacter(
+        &self, freq: Duration, policy: CompactionPolicy, cancel: CancelSig,
+    ) -> tokio::task::JoinHandle<DbResult<usize>>;
+}
+
+#[cfg(feature = "tokio")]
+impl<D> AsyncBackgroundCompacter for crate::AsyncDb<D>
+where
+    D: Db + Send + Sync + 'static,
+{
+    fn begin_policy_compacter(
+        &self, freq: Duration, policy: CompactionPolicy, cancel: CancelSig,
+    ) -> tokio::task::JoinHandle<DbResult<usize>> {
+        let db = self.clone();
+        tokio::spawn(async move {
             let mut count = 0;
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + freq, freq);
             loop {
-                thread::sleep(freq);
-
+                let canceled = cancel.0.notify.notified();
                 if cancel.is_canceled() {
                     return Ok(count);
                 }
 
-                db.lock()?.compact_log()?;
-                count += 1;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = canceled => return Ok(count),
+                }
+
+                if db.compact_if(&policy).await? {
+                    count += 1;
+                }
             }
         })
     }
@@ -0,0 +1,82 @@
+use crate::{CompactionPolicy, Config, Db, DbResult, Snapshot};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A cloneable, thread-safe handle to a [Db].
+///
+/// Any number of threads can [ConcurrentDb::read] the tables at the same time, while writes are
+/// serialized through [ConcurrentDb::transaction] (or [ConcurrentDb::write]).
+///
+/// Readers observe transaction boundaries: a `transaction` holds the write lock from
+/// `begin_transaction()` until its log entry has been handed to the [crate::Logger], so a reader
+/// will either see all of a transaction's writes or none of them. Writes made through
+/// [ConcurrentDb::write] are only grouped by the lifetime of that guard.
+#[derive(Debug)]
+pub struct ConcurrentDb<D> {
+    inner: Arc<RwLock<D>>,
+}
+
+impl<D> Clone for ConcurrentDb<D> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<D> ConcurrentDb<D>
+where
+    D: Db,
+{
+    pub fn init(config: Config) -> DbResult<Self> {
+        Ok(Self::new(D::init(config)?))
+    }
+
+    pub fn new(db: D) -> Self {
+        Self { inner: Arc::new(RwLock::new(db)) }
+    }
+
+    /// Shared access to the tables, blocks while a writer is active.
+    pub fn read(&self) -> DbResult<RwLockReadGuard<'_, D>> {
+        Ok(self.inner.read()?)
+    }
+
+    /// Exclusive access to the tables. Prefer [ConcurrentDb::transaction] unless you need to
+    /// manage transactions yourself.
+    pub fn write(&self) -> DbResult<RwLockWriteGuard<'_, D>> {
+        Ok(self.inner.write()?)
+    }
+
+    /// Run `f` inside a transaction while holding the write lock. The transaction is committed
+    /// before any reader can observe its effects, regardless of whether `f` succeeded.
+    pub fn transaction<F, R>(&self, f: F) -> DbResult<R>
+    where
+        F: FnOnce(&mut D) -> DbResult<R>,
+    {
+        let mut db = self.inner.write()?;
+        let tx = db.begin_transaction()?;
+        let result = f(&mut db);
+        tx.drop_safely()?;
+        result
+    }
+
+    /// A consistent view of every table as of the last committed transaction, see [Snapshot].
+    pub fn snapshot(&self) -> DbResult<Snapshot<D>> {
+        self.read()?.snapshot()
+    }
+
+    /// Compact the log from a [Snapshot], only briefly blocking writers.
+    pub fn compact_log(&self) -> DbResult<()> {
+        self.snapshot()?.compact_log()
+    }
+
+    /// Compact the log if `policy` says it's time to, returns whether a compaction happened.
+    pub fn compact_if(&self, policy: &CompactionPolicy) -> DbResult<bool> {
+        let snapshot = {
+            let db = self.read()?;
+            if !policy.should_compact(&db.log_stats()?) {
+                return Ok(false);
+            }
+            db.snapshot()?
+        };
+        snapshot.compact_log()?;
+        Ok(true)
+    }
+}
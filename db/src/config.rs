@@ -1,5 +1,7 @@
+use crate::descriptor::SchemaDescriptor;
 use crate::errors::{DbError, DbResult};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// db-rs's config that describes where the log file should be and how the database should behave.
 /// use [Config::in_folder] as a starting point.
@@ -26,6 +28,44 @@ pub struct Config {
     /// if using fs_locks, should we block while trying to aquire a lock? Default: false
     pub fs_locks_block: bool,
 
+    /// when should writes reach the log file? Default: [FlushPolicy::Immediate]
+    pub flush_policy: FlushPolicy,
+
+    /// the largest the log may grow to in bytes, writes that would grow it further fail with
+    /// [DbError::QuotaExceeded]. Default: None
+    pub max_log_size: Option<u64>,
+
+    /// should db-rs compact the log and try again before failing a write with
+    /// [DbError::QuotaExceeded]? Compacting this way replays the log, so it temporarily needs
+    /// memory for a second copy of the tables. Default: false
+    pub compact_on_quota: bool,
+
+    /// how much free disk space in bytes compaction requires before it starts, as the old log and
+    /// the new one exist side by side until it's done. Default: None
+    pub min_free_space: Option<u64>,
+
+    /// the name of the log file, without the `.db` extension. Defaults to the name of the schema,
+    /// which is the name of the struct unless it's set with `#[schema(name = "...")]`. Default: None
+    pub file_stem: Option<String>,
+
+    /// should db-rs refuse to create a new log when the folder already has `.db` files of another
+    /// name? Renaming a schema changes the name of its log, and without this its data is silently
+    /// left behind while a new, empty, db is created. Fails with [DbError::OtherLogsExist].
+    /// Default: false
+    pub guard_new_db: bool,
+
+    /// should new logs use wide table ids? Logs only have room for 254 tables without them, and
+    /// once a log has them it can't be read by versions of db-rs that predate them. Existing logs
+    /// are upgraded when they're compacted, which `#[derive(Schema)]` does right away for schemas
+    /// marked with `#[schema(wide_table_ids)]`, the way a schema sets this. Default: false
+    pub wide_table_ids: bool,
+
+    /// a description of the schema to store in the header of the log, so tools can make sense of
+    /// it without the schema's types. Existing logs get it, or have it replaced, when they're
+    /// compacted, which `#[derive(Schema)]` does right away for schemas marked with
+    /// `#[schema(describe)]`, the way a schema sets this. Default: None
+    pub schema_descriptor: Option<SchemaDescriptor>,
+
     #[doc(hidden)]
     pub schema_name: Option<String>,
 }
@@ -41,19 +81,25 @@ impl Config {
             no_io: false,
             fs_locks: true,
             fs_locks_block: false,
+            flush_policy: FlushPolicy::Immediate,
+            max_log_size: None,
+            compact_on_quota: false,
+            min_free_space: None,
+            file_stem: None,
+            guard_new_db: false,
+            wide_table_ids: false,
+            schema_descriptor: None,
         }
     }
 
     pub fn no_io() -> Self {
         Self {
-            path: Default::default(),
-            schema_name: None,
             create_path: false,
             create_db: false,
             read_only: true,
             no_io: true,
             fs_locks: false,
-            fs_locks_block: false,
+            ..Self::base()
         }
     }
 
@@ -64,30 +110,71 @@ impl Config {
         Self { path: PathBuf::from(p.as_ref()), ..Self::base() }
     }
 
+    /// the name of the log file, without an extension: [Config::file_stem] if it's set, otherwise
+    /// the name of the schema
+    fn stem(&self) -> DbResult<&str> {
+        self.file_stem
+            .as_deref()
+            .or(self.schema_name.as_deref())
+            .ok_or(DbError::SchemaNameMissing)
+    }
+
     pub fn db_location_v2(&self) -> DbResult<PathBuf> {
-        let name = self.schema_name.as_ref().ok_or(DbError::Unexpected(
-            "Schema name not populated! db-rs-derive should have done this",
-        ))?;
-        let mut pathbuf = self.path.clone();
-        pathbuf.push(format!("{name}.db"));
-        Ok(pathbuf)
+        Ok(self.path.join(format!("{}.db", self.stem()?)))
     }
 
     pub fn db_location_v1(&self) -> DbResult<PathBuf> {
-        let name = self.schema_name.as_ref().ok_or(DbError::Unexpected(
-            "Schema name not populated! db-rs-derive should have done this",
-        ))?;
-        let mut pathbuf = self.path.clone();
-        pathbuf.push(name);
-        Ok(pathbuf)
+        Ok(self.path.join(self.stem()?))
     }
 
     pub fn compaction_location(&self) -> DbResult<PathBuf> {
-        let name = self.schema_name.as_ref().ok_or(DbError::Unexpected(
-            "Schema name not populated! db-rs-derive should have done this",
-        ))?;
-        let mut pathbuf = self.path.clone();
-        pathbuf.push(format!("{name}.db.tmp"));
-        Ok(pathbuf)
+        Ok(self.path.join(format!("{}.db.tmp", self.stem()?)))
+    }
+
+    pub fn migration_location(&self) -> DbResult<PathBuf> {
+        Ok(self.path.join(format!("{}.db.migrating", self.stem()?)))
+    }
+
+    /// where a log is moved to once it's been migrated to a newer schema
+    pub fn archive_location(&self) -> DbResult<PathBuf> {
+        Ok(self.path.join(format!("{}.db.archived", self.stem()?)))
+    }
+
+    /// The config of another schema in the same folder, see [crate::migration].
+    pub(crate) fn for_schema(&self, name: &str) -> Self {
+        Self {
+            schema_name: Some(name.to_string()),
+            file_stem: None,
+            schema_descriptor: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Describes when writes are flushed from db-rs's write buffer to the log file. Regardless of
+/// policy, the buffer is flushed by [crate::Db::flush] and when the db is dropped.
+#[derive(Clone, Debug)]
+pub enum FlushPolicy {
+    /// every write, or transaction, reaches the log file before it returns
+    Immediate,
+
+    /// writes are buffered in memory and flushed once `max_bytes` are buffered, or once the oldest
+    /// buffered write is older than `max_delay`. A background thread enforces `max_delay` while
+    /// no writes are happening, except on wasm where it's only checked when a write happens.
+    /// Writes that are still in the buffer are lost if the process exits without dropping the db.
+    Buffered { max_bytes: usize, max_delay: Option<Duration> },
+}
+
+impl FlushPolicy {
+    pub(crate) fn should_flush(&self, buffered: usize, oldest_write: Instant) -> bool {
+        match self {
+            FlushPolicy::Immediate => true,
+            FlushPolicy::Buffered { max_bytes, max_delay } => {
+                buffered >= *max_bytes
+                    || max_delay
+                        .map(|delay| oldest_write.elapsed() >= delay)
+                        .unwrap_or(false)
+            }
+        }
     }
 }
@@ -1,16 +1,106 @@
-use crate::{Config, DbResult, Logger, TxHandle};
+use crate::descriptor::TableDescriptor;
+use crate::logger::{LogFormat, Replay};
+use crate::{
+    CompactionPolicy, Config, DbResult, DbStats, LogStats, Logger, Snapshot, TableId, TxHandle,
+};
+use std::io::Write;
 
 pub trait Db: Sized {
     fn init(location: Config) -> DbResult<Self>;
-    fn compact_log(&mut self) -> DbResult<()>;
+    /// The name of the schema, which names its log file.
+    #[doc(hidden)]
+    fn schema_name() -> &'static str;
+    /// Replay the log `logger` was opened with into a fresh set of tables.
+    #[doc(hidden)]
+    fn load(logger: Logger) -> DbResult<Self>;
+    /// Whether there's a log in `config`'s folder that `init` would open, either this schema's own
+    /// or one it migrates from.
+    #[doc(hidden)]
+    fn log_exists(config: &Config) -> DbResult<bool> {
+        crate::migration::own_log_exists::<Self>(config)
+    }
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()>;
+    fn snapshot(&self) -> DbResult<Snapshot<Self>>;
     fn get_logger(&self) -> &Logger;
+    fn compact_log(&mut self) -> DbResult<()> {
+        self.get_logger()
+            .compact_log(|out| self.write_compact_repr(out))
+    }
+    /// Compact the log if `policy` says it's time to, returns whether a compaction happened.
+    fn compact_if(&mut self, policy: &CompactionPolicy) -> DbResult<bool> {
+        if !policy.should_compact(&self.log_stats()?) {
+            return Ok(false);
+        }
+        self.compact_log()?;
+        Ok(true)
+    }
+    fn log_stats(&self) -> DbResult<LogStats> {
+        self.get_logger().log_stats()
+    }
+    /// [Db::log_stats] along with per-table numbers, gathered during replay and writes.
+    fn stats(&self) -> DbResult<DbStats> {
+        self.get_logger().stats()
+    }
+    fn flush(&self) -> DbResult<()> {
+        self.get_logger().flush()
+    }
     fn config(&self) -> DbResult<Config> {
         self.get_logger().config()
     }
     fn incomplete_write(&self) -> DbResult<bool> {
         self.get_logger().incomplete_write()
     }
+    /// Truncate a partially written entry from the end of the log, see [Logger::recover], and
+    /// reload the tables from the log. Writes that didn't make it into the log are undone.
+    fn recover(&mut self) -> DbResult<()> {
+        let logger = self.get_logger().clone();
+        logger.recover()?;
+        *self = Self::load(logger)?;
+        Ok(())
+    }
+    /// Compact the log of `logger` by replaying it into a second copy of the tables, used when a
+    /// write exceeds [Config::max_log_size] as the tables themselves aren't reachable from there.
+    #[doc(hidden)]
+    fn compact_from_log(logger: &Logger) -> DbResult<()> {
+        Self::load(logger.clone())?.compact_log()
+    }
     fn begin_transaction(&mut self) -> DbResult<TxHandle> {
         self.get_logger().begin_tx()
     }
 }
+
+/// The tables of a schema laid out over a range of table ids, starting at the id they're given.
+/// `#[derive(Schema)]` implements this, it's what lets one schema be mounted as a field of another
+/// with `#[schema(nested)]`, its tables taking the next [Tables::TABLE_COUNT] ids of the parent.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a db-rs schema",
+    label = "fields marked `#[schema(nested)]` must be schemas",
+    note = "add `#[derive(Schema)]` to `{Self}`, or remove `#[schema(nested)]` if it's a table"
+)]
+pub trait Tables: Sized {
+    /// How many table ids the tables take, counting the tables of nested schemas.
+    const TABLE_COUNT: usize;
+    /// Fresh tables with ids from `first_id`, writing to `logger`.
+    fn init_tables(first_id: TableId, logger: Logger) -> Self;
+    /// Hand `entry` to the table it belongs to, reporting it to `replay`.
+    #[doc(hidden)]
+    fn replay_entry(
+        &mut self, first_id: TableId, replay: &mut Replay, entry: &LogFormat,
+    ) -> DbResult<()>;
+    /// The ids and field names of the tables, see [Replay::finish].
+    #[doc(hidden)]
+    fn table_names(first_id: TableId, names: &mut Vec<(TableId, &'static str)>);
+    /// Describe the tables, their names prefixed with `prefix`, see [crate::descriptor::SchemaDescriptor::of].
+    #[doc(hidden)]
+    fn describe_tables(first_id: TableId, prefix: &str, tables: &mut Vec<TableDescriptor>);
+    /// Write the compact representation of every table, see [crate::table::Table::write_compact_repr].
+    fn write_tables(&self, out: &mut dyn Write) -> DbResult<()>;
+    /// Snapshots of the tables with ids from `first_id`, see [crate::table::Table::snapshot].
+    fn snapshot_tables(&self, first_id: TableId, logger: Logger) -> DbResult<Self>;
+    /// The logger the tables write to.
+    fn tables_logger(&self) -> &Logger;
+    /// Replace the tables with those of `from`, keeping the fields that aren't tables, see
+    /// [Db::recover].
+    #[doc(hidden)]
+    fn replace_tables(&mut self, from: Self);
+}
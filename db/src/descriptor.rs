@@ -0,0 +1,82 @@
+//! A description of a schema stored in the header of its log, so tools can label the entries of a
+//! log without the schema's types. `#[derive(Schema)]` stores it for schemas marked with
+//! `#[schema(describe)]`, see [Config::schema_descriptor].
+
+use crate::logger::Logger;
+use crate::{Config, Db, DbResult, TableId, Tables};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The tables of a schema, see [crate::descriptor].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaDescriptor {
+    /// the name of the schema, see [Db::schema_name]
+    pub name: String,
+    /// the tables of the schema in the order of their ids, those of nested schemas included
+    pub tables: Vec<TableDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDescriptor {
+    /// the name of the field, prefixed with the fields of the nested schemas it's in, like
+    /// `files.docs`
+    pub name: String,
+    pub id: TableId,
+    pub kind: TableKind,
+}
+
+/// Which table a field is, along with the full names of the types it holds, see
+/// [crate::table::Table::kind].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TableKind {
+    Single {
+        value: String,
+    },
+    List {
+        value: String,
+    },
+    LookupTable {
+        key: String,
+        value: String,
+    },
+    LookupList {
+        key: String,
+        value: String,
+    },
+    LookupSet {
+        key: String,
+        value: String,
+    },
+    /// a table implemented outside of db-rs
+    Custom {
+        type_name: String,
+    },
+}
+
+impl SchemaDescriptor {
+    /// The description of `D`, as `#[derive(Schema)]` stores it.
+    pub fn of<D: Db + Tables>() -> Self {
+        let mut tables = vec![];
+        D::describe_tables(1, "", &mut tables);
+        Self { name: D::schema_name().to_string(), tables }
+    }
+
+    /// Read the description stored in the log at `path`, None if it doesn't have one. The log
+    /// isn't locked, so this works on a log that's in use.
+    pub fn read<P: AsRef<Path>>(path: P) -> DbResult<Option<Self>> {
+        let path = path.as_ref();
+        let mut config = Config::in_folder(path.parent().unwrap_or(path));
+        config.file_stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        match Logger::read_metadata(&config)? {
+            Some(meta) => meta.schema_descriptor(),
+            None => Ok(None),
+        }
+    }
+
+    /// The table with `id`.
+    pub fn table(&self, id: TableId) -> Option<&TableDescriptor> {
+        self.tables.iter().find(|table| table.id == id)
+    }
+}
@@ -0,0 +1,354 @@
+//! Tables that are defined at runtime rather than by a `#[derive(Schema)]` struct, see [DynDb].
+
+use crate::descriptor::{TableDescriptor, TableKind};
+use crate::logger::Replay;
+use crate::table::Table;
+use crate::{
+    Config, Db, DbError, DbResult, List, Logger, LookupList, LookupSet, LookupTable, Single,
+    Snapshot, TableId,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::io::Write;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// the table that maps the names of tables to their ids and kinds
+const REGISTRY: TableId = 1;
+
+/// the largest id a log without [Config::wide_table_ids] has room for
+const MAX_NARROW_ID: TableId = u8::MAX as TableId - 1;
+
+/// An entry of a table that wasn't opened since the log was loaded, and its offset in the log.
+type Pending = (u64, Vec<u8>);
+
+/// Replays the entries of the table with the id and name into the type the table was opened as,
+/// and writes its compact repr, see [compact_entries].
+type Compact = fn(TableId, &str, &Logger, &[Pending], &mut dyn Write) -> DbResult<()>;
+
+/// A db whose tables are registered by name at runtime, for plugins and other code that can't
+/// define a schema at compile time.
+///
+/// The first time a table is opened it's given the next free id, which is recorded in the log
+/// along with its [TableKind], so it keeps its id. Opening it as another kind of table, or with
+/// other types than it was registered with, fails with [DbError::TableKindMismatch]. Types are
+/// compared by the names in the [TableKind], which change when a type is moved or renamed, use
+/// [DynDb::retype] to record the new ones.
+///
+/// Entries of tables that haven't been opened since the db was loaded are kept as they are, and
+/// survive compaction. Once a table was opened, compacting the log, including when a write
+/// exceeds [Config::max_log_size], compacts its entries too, even from a copy of the db that
+/// doesn't have it open.
+///
+/// ```ignore
+/// let mut db = DynDb::init(Config::in_folder("/tmp/plugins"))?;
+/// db.lookup::<String, u64>("word_counts")?.insert("hello".into(), 1)?;
+/// ```
+///
+/// Transactions, compaction, snapshots and the rest of [Db] work as they do for derived schemas.
+/// The log is named `dyn_db.db` unless [Config::file_stem] says otherwise.
+pub struct DynDb {
+    registry: LookupTable<String, TableDescriptor>,
+    tables: BTreeMap<TableId, Box<dyn DynTable>>,
+    /// entries of tables that weren't opened since the log was loaded, by table id
+    pending: BTreeMap<TableId, Vec<Pending>>,
+    /// how to compact the tables that were opened, shared with the compacter of the log
+    compacts: Arc<Mutex<BTreeMap<TableId, Compact>>>,
+    logger: Logger,
+}
+
+impl DynDb {
+    /// The table registered as `name` with `T`'s [Table::kind], registering it if it isn't yet.
+    pub fn table<T>(&mut self, name: &str) -> DbResult<&mut T>
+    where
+        T: Table + Send + Sync + 'static,
+    {
+        let kind = T::kind();
+        let id = match self.registry.get().get(name) {
+            Some(registered) if registered.kind != kind => {
+                return Err(mismatch(&self.registry, name, kind));
+            }
+            Some(registered) => registered.id,
+            None => self.register(name, kind.clone())?,
+        };
+
+        if !self.tables.contains_key(&id) {
+            let mut table = T::init(id, self.logger.clone());
+            // the entries are only dropped once they're all in the table
+            for (offset, entry) in self.pending.get(&id).into_iter().flatten() {
+                table
+                    .handle_event(entry)
+                    .map_err(|err| err.in_entry(id, name.to_string(), *offset))?;
+            }
+            self.pending.remove(&id);
+            self.tables.insert(id, Box::new(table));
+            self.compacts.lock()?.insert(id, compact_entries::<T>);
+        }
+
+        let table = self.tables.get_mut(&id);
+        match table.and_then(|table| table.as_any_mut().downcast_mut()) {
+            Some(table) => Ok(table),
+            None => Err(mismatch(&self.registry, name, kind)),
+        }
+    }
+
+    /// Record `T`'s [Table::kind] as that of the table `name` and open it, for when the types it
+    /// holds were moved or renamed without changing how they're serialized. `T` must be the same
+    /// kind of table as the one that was registered, and the table mustn't be open as another type.
+    pub fn retype<T>(&mut self, name: &str) -> DbResult<&mut T>
+    where
+        T: Table + Send + Sync + 'static,
+    {
+        let kind = T::kind();
+        let Some(registered) = self.registry.get().get(name).cloned() else {
+            return self.table(name);
+        };
+        let open_as_other = self
+            .tables
+            .get(&registered.id)
+            .is_some_and(|table| !table.as_any().is::<T>());
+        if mem::discriminant(&registered.kind) != mem::discriminant(&kind) || open_as_other {
+            return Err(mismatch(&self.registry, name, kind));
+        }
+
+        if registered.kind != kind {
+            self.registry
+                .insert(name.to_string(), TableDescriptor { kind, ..registered })?;
+        }
+        self.table(name)
+    }
+
+    pub fn single<T>(&mut self, name: &str) -> DbResult<&mut Single<T>>
+    where
+        T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        self.table(name)
+    }
+
+    pub fn list<T>(&mut self, name: &str) -> DbResult<&mut List<T>>
+    where
+        T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        self.table(name)
+    }
+
+    pub fn lookup<K, V>(&mut self, name: &str) -> DbResult<&mut LookupTable<K, V>>
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+        V: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        self.table(name)
+    }
+
+    pub fn lookup_list<K, V>(&mut self, name: &str) -> DbResult<&mut LookupList<K, V>>
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+        V: Serialize + DeserializeOwned + Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.table(name)
+    }
+
+    pub fn lookup_set<K, V>(&mut self, name: &str) -> DbResult<&mut LookupSet<K, V>>
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+        V: Serialize + DeserializeOwned + Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.table(name)
+    }
+
+    /// The table `name` if it was opened as a `T`, useful for reading from a [Snapshot], which
+    /// has the tables that were open when it was taken.
+    pub fn get<T: 'static>(&self, name: &str) -> Option<&T> {
+        let id = self.registry.get().get(name)?.id;
+        self.tables.get(&id)?.as_any().downcast_ref()
+    }
+
+    /// The tables that were registered, in the order of their ids.
+    pub fn registered(&self) -> Vec<&TableDescriptor> {
+        let mut tables: Vec<_> = self.registry.get().values().collect();
+        tables.sort_by_key(|table| table.id);
+        tables
+    }
+
+    fn register(&mut self, name: &str, kind: TableKind) -> DbResult<TableId> {
+        let last = self.registry.get().values().map(|table| table.id).max();
+        let id = last.unwrap_or(REGISTRY) + 1;
+        if id > MAX_NARROW_ID && !self.logger.config()?.wide_table_ids {
+            return Err(DbError::WideTableId { id });
+        }
+
+        let table = TableDescriptor { name: name.to_string(), id, kind };
+        self.registry.insert(name.to_string(), table)?;
+        Ok(id)
+    }
+}
+
+impl Db for DynDb {
+    fn init(mut config: Config) -> DbResult<Self> {
+        config.schema_name = Some(Self::schema_name().to_string());
+        let log = Logger::init(config)?;
+        let mut db = Self::load(log)?;
+        let compacts = db.compacts.clone();
+        db.logger.set_compacter_with(move |logger| {
+            let mut db = Self::load(logger.clone())?;
+            db.compacts = compacts.clone();
+            db.compact_log()
+        })?;
+        db.logger
+            .measure_live_data(|out| db.write_compact_repr(out))?;
+        if db.logger.metadata_outdated()? {
+            db.compact_log()?;
+        }
+        Ok(db)
+    }
+
+    fn schema_name() -> &'static str {
+        "dyn_db"
+    }
+
+    fn load(logger: Logger) -> DbResult<Self> {
+        let mut replay = Replay::start();
+        let log_data = logger.get_bytes()?;
+        let log_entries = logger.get_entries(&log_data)?;
+
+        let mut registry = LookupTable::init(REGISTRY, logger.clone());
+        let mut pending: BTreeMap<TableId, Vec<Pending>> = BTreeMap::new();
+        for entry in log_entries {
+            if entry.table_id == REGISTRY {
+                replay.entry(REGISTRY, "registry", entry.offset, || {
+                    registry.handle_event(entry.bytes)
+                })?;
+            } else {
+                pending
+                    .entry(entry.table_id)
+                    .or_default()
+                    .push((entry.offset, entry.bytes.to_vec()));
+            }
+        }
+        replay.finish(&[(REGISTRY, "registry")]);
+
+        Ok(Self {
+            registry,
+            tables: BTreeMap::new(),
+            pending,
+            compacts: Default::default(),
+            logger,
+        })
+    }
+
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        self.registry.write_compact_repr(out)?;
+        for table in self.tables.values() {
+            table.write_dyn(out)?;
+        }
+        let compacts = self.compacts.lock()?;
+        for (id, entries) in &self.pending {
+            match compacts.get(id) {
+                Some(compact) => {
+                    compact(*id, name_of(&self.registry, *id), &self.logger, entries, out)?
+                }
+                None => {
+                    for (_, entry) in entries {
+                        Logger::write_entry(out, *id, entry)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn recover(&mut self) -> DbResult<()> {
+        self.logger.recover()?;
+        let compacts = self.compacts.clone();
+        *self = Self::load(self.logger.clone())?;
+        // the compacter of the log keeps the tables that were opened before
+        self.compacts = compacts;
+        Ok(())
+    }
+
+    fn snapshot(&self) -> DbResult<Snapshot<Self>> {
+        Snapshot::new(&self.logger, |logger| {
+            let mut tables = BTreeMap::new();
+            for (id, table) in &self.tables {
+                tables.insert(*id, table.snapshot_dyn(*id, logger.clone())?);
+            }
+            Ok(Self {
+                registry: self.registry.snapshot(REGISTRY, logger.clone())?,
+                tables,
+                pending: self.pending.clone(),
+                compacts: self.compacts.clone(),
+                logger,
+            })
+        })
+    }
+
+    fn get_logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+/// A [Table] whose type is only known to the code that opened it.
+trait DynTable: Send + Sync {
+    fn write_dyn(&self, out: &mut dyn Write) -> DbResult<()>;
+    fn snapshot_dyn(&self, table_id: TableId, logger: Logger) -> DbResult<Box<dyn DynTable>>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T> DynTable for T
+where
+    T: Table + Send + Sync + 'static,
+{
+    fn write_dyn(&self, out: &mut dyn Write) -> DbResult<()> {
+        self.write_compact_repr(out)
+    }
+
+    fn snapshot_dyn(&self, table_id: TableId, logger: Logger) -> DbResult<Box<dyn DynTable>> {
+        Ok(Box::new(self.snapshot(table_id, logger)?))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The error for opening the registered table `name` as a `requested` table.
+fn mismatch(
+    registry: &LookupTable<String, TableDescriptor>, name: &str, requested: TableKind,
+) -> DbError {
+    DbError::TableKindMismatch {
+        table: name.to_string(),
+        registered: Box::new(registry.get()[name].kind.clone()),
+        requested: Box::new(requested),
+    }
+}
+
+/// The name the table `id` was registered as.
+fn name_of(registry: &LookupTable<String, TableDescriptor>, id: TableId) -> &str {
+    registry
+        .get()
+        .values()
+        .find(|table| table.id == id)
+        .map(|table| table.name.as_str())
+        .unwrap_or_default()
+}
+
+/// Compact `entries` of the table `id` as a `T`, see [Compact].
+fn compact_entries<T: Table>(
+    id: TableId, name: &str, logger: &Logger, entries: &[Pending], out: &mut dyn Write,
+) -> DbResult<()> {
+    let mut table = T::init(id, logger.clone());
+    for (offset, entry) in entries {
+        table
+            .handle_event(entry)
+            .map_err(|err| err.in_entry(id, name.to_string(), *offset))?;
+    }
+    table.write_compact_repr(out)
+}
@@ -1,6 +1,10 @@
+use crate::descriptor::TableKind;
+use crate::TableId;
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt::Display;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::PoisonError;
 
 pub type DbResult<T> = Result<T, DbError>;
@@ -11,6 +15,215 @@ pub enum DbError {
     Io(io::Error),
     Bincode(bincode::Error),
     MutexPoisoned,
+    /// an entry was larger than the log can frame, compacting the log upgrades it to a format
+    /// without this limit
+    EntryTooLarge {
+        size: u64,
+        max: u64,
+    },
+    /// an entry of table `id` was written to a log without wide table ids, which only has room
+    /// for ids below 255, see [crate::Config::wide_table_ids]
+    WideTableId {
+        id: TableId,
+    },
+    /// a [crate::DynDb] table was opened as another kind of table than it was registered as, or
+    /// as other types than it's already open as
+    TableKindMismatch {
+        table: String,
+        registered: Box<TableKind>,
+        requested: Box<TableKind>,
+    },
+    /// the log is locked by another process, or by another db in this one
+    Locked {
+        path: PathBuf,
+    },
+    /// [crate::Config::fs_locks] is set on a platform without file locks
+    LocksUnsupported,
+    /// the log was written by a version of db-rs that uses a format this one can't read
+    UnsupportedVersion {
+        path: PathBuf,
+        expected: u8,
+        found: u8,
+    },
+    /// the log doesn't follow its format at `offset`
+    Corrupt {
+        path: PathBuf,
+        offset: u64,
+        reason: &'static str,
+    },
+    /// the entry at `offset` couldn't be read or applied by the table it belongs to, usually
+    /// because the table's type changed without a migration
+    CorruptEntry {
+        table_id: TableId,
+        table: Cow<'static, str>,
+        offset: u64,
+        source: Box<DbError>,
+    },
+    /// the entry at `offset` belongs to a table the schema doesn't have, usually because a table
+    /// was removed from the schema without a migration
+    UnknownTable {
+        table_id: TableId,
+        offset: u64,
+    },
+    /// an earlier io error may have left a partial entry at the end of the log, so it stopped
+    /// accepting writes until [crate::Db::recover] is called or the db is reopened
+    LogPoisoned {
+        cause: String,
+    },
+    /// the write would grow the log to `size` bytes, beyond [crate::Config::max_log_size]
+    QuotaExceeded {
+        size: u64,
+        max: u64,
+    },
+    /// less than [crate::Config::min_free_space] was available, so compaction wasn't attempted
+    InsufficientSpace {
+        available: u64,
+        required: u64,
+    },
+    /// the disk filled up while writing `path`, which was removed again
+    DiskFull {
+        path: PathBuf,
+    },
+    /// an index was outside of a list of `len` elements
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    /// [crate::Config::guard_new_db] stopped `path` from being created as the folder has `others`
+    OtherLogsExist {
+        path: PathBuf,
+        others: Vec<PathBuf>,
+    },
+    /// the log of `from` has to be migrated to `to`, which a read only config, or one that
+    /// doesn't create logs, doesn't allow, see [crate::migration::migrate]
+    MigrationPending {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// a background compaction panicked, the log is left as it was before it started
+    CompacterPanicked,
+    /// opening the db of a [crate::AsyncDb] panicked
+    InitPanicked,
+    /// an [crate::AsyncDb] transaction panicked, the writes it made before are committed
+    TxPanicked,
+    /// the writer of an [crate::AsyncDb] was closed, or stopped before the transaction committed
+    WriterGone,
+    /// [crate::Config::schema_name] wasn't set, `#[derive(Schema)]` takes care of this
+    SchemaNameMissing,
+    /// a [crate::Snapshot] can't compact a log that was compacted after it was taken
+    SnapshotOutdated,
+    /// snapshots can't be taken while the writes of a transaction are still pending
+    TransactionInProgress,
+}
+
+impl DbError {
+    /// Whether the log couldn't be opened because another db holds its lock, in which case
+    /// retrying later (or with [crate::Config::fs_locks_block]) may succeed.
+    pub fn is_locked(&self) -> bool {
+        matches!(self, DbError::Locked { .. })
+    }
+
+    /// Whether the contents of the log couldn't be understood, see [DbError::Corrupt],
+    /// [DbError::CorruptEntry] and [DbError::UnknownTable].
+    pub fn is_corrupt(&self) -> bool {
+        matches!(
+            self,
+            DbError::Corrupt { .. } | DbError::CorruptEntry { .. } | DbError::UnknownTable { .. }
+        )
+    }
+
+    /// Report an error of the table `table` reading the entry at `offset` as
+    /// [DbError::CorruptEntry], if it means the entry couldn't be read or applied.
+    pub(crate) fn in_entry(
+        self, table_id: TableId, table: impl Into<Cow<'static, str>>, offset: u64,
+    ) -> Self {
+        match self {
+            err @ (DbError::Bincode(_) | DbError::IndexOutOfBounds { .. }) => {
+                DbError::CorruptEntry {
+                    table_id,
+                    table: table.into(),
+                    offset,
+                    source: Box::new(err),
+                }
+            }
+            err => err,
+        }
+    }
+
+    /// Report running out of space while writing `path` as [DbError::DiskFull].
+    pub(crate) fn disk_full_at(self, path: &Path) -> Self {
+        match self {
+            DbError::Io(err) if err.kind() == io::ErrorKind::StorageFull => {
+                DbError::DiskFull { path: path.to_path_buf() }
+            }
+            err => err,
+        }
+    }
+
+    /// A copy of this error for reporting it to more than one caller. Wrapped errors that aren't
+    /// `Clone` are reconstructed from their kind and message.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            DbError::Unexpected(u) => DbError::Unexpected(u),
+            DbError::Io(i) => DbError::Io(io::Error::new(i.kind(), i.to_string())),
+            DbError::Bincode(b) => {
+                DbError::Bincode(Box::new(bincode::ErrorKind::Custom(b.to_string())))
+            }
+            DbError::MutexPoisoned => DbError::MutexPoisoned,
+            DbError::EntryTooLarge { size, max } => {
+                DbError::EntryTooLarge { size: *size, max: *max }
+            }
+            DbError::WideTableId { id } => DbError::WideTableId { id: *id },
+            DbError::TableKindMismatch { table, registered, requested } => {
+                DbError::TableKindMismatch {
+                    table: table.clone(),
+                    registered: registered.clone(),
+                    requested: requested.clone(),
+                }
+            }
+            DbError::Locked { path } => DbError::Locked { path: path.clone() },
+            DbError::LocksUnsupported => DbError::LocksUnsupported,
+            DbError::UnsupportedVersion { path, expected, found } => DbError::UnsupportedVersion {
+                path: path.clone(),
+                expected: *expected,
+                found: *found,
+            },
+            DbError::Corrupt { path, offset, reason } => {
+                DbError::Corrupt { path: path.clone(), offset: *offset, reason }
+            }
+            DbError::CorruptEntry { table_id, table, offset, source } => DbError::CorruptEntry {
+                table_id: *table_id,
+                table: table.clone(),
+                offset: *offset,
+                source: Box::new(source.duplicate()),
+            },
+            DbError::UnknownTable { table_id, offset } => {
+                DbError::UnknownTable { table_id: *table_id, offset: *offset }
+            }
+            DbError::IndexOutOfBounds { index, len } => {
+                DbError::IndexOutOfBounds { index: *index, len: *len }
+            }
+            DbError::LogPoisoned { cause } => DbError::LogPoisoned { cause: cause.clone() },
+            DbError::QuotaExceeded { size, max } => {
+                DbError::QuotaExceeded { size: *size, max: *max }
+            }
+            DbError::InsufficientSpace { available, required } => {
+                DbError::InsufficientSpace { available: *available, required: *required }
+            }
+            DbError::DiskFull { path } => DbError::DiskFull { path: path.clone() },
+            DbError::OtherLogsExist { path, others } => {
+                DbError::OtherLogsExist { path: path.clone(), others: others.clone() }
+            }
+            DbError::MigrationPending { from, to } => DbError::MigrationPending { from, to },
+            DbError::CompacterPanicked => DbError::CompacterPanicked,
+            DbError::InitPanicked => DbError::InitPanicked,
+            DbError::TxPanicked => DbError::TxPanicked,
+            DbError::WriterGone => DbError::WriterGone,
+            DbError::SchemaNameMissing => DbError::SchemaNameMissing,
+            DbError::SnapshotOutdated => DbError::SnapshotOutdated,
+            DbError::TransactionInProgress => DbError::TransactionInProgress,
+        }
+    }
 }
 
 impl From<bincode::Error> for DbError {
@@ -37,7 +250,86 @@ impl Display for DbError {
             DbError::Unexpected(u) => write!(f, "unexpected error: {u}"),
             DbError::Io(i) => write!(f, "io error: {i}"),
             DbError::Bincode(b) => write!(f, "bincode error: {b}"),
-            DbError::MutexPoisoned => write!(f, "mutex poisoned"),
+            DbError::MutexPoisoned => {
+                write!(f, "mutex poisoned, a thread panicked while holding a lock on the db")
+            }
+            DbError::EntryTooLarge { size, max } => {
+                write!(f, "entry of {size} bytes is larger than the maximum of {max} bytes")
+            }
+            DbError::WideTableId { id } => {
+                write!(f, "table id {id} doesn't fit in a log without wide table ids")
+            }
+            DbError::TableKindMismatch { table, registered, requested } => write!(
+                f,
+                "table {table} was registered as {registered:?}, it can't be opened as {requested:?}"
+            ),
+            DbError::Locked { path } => {
+                write!(f, "{} is locked by another db", path.display())
+            }
+            DbError::LocksUnsupported => write!(f, "file locks are not supported on this platform"),
+            DbError::UnsupportedVersion { path, expected, found } => write!(
+                f,
+                "{} has log version {found}, expected at most version {expected}",
+                path.display()
+            ),
+            DbError::Corrupt { path, offset, reason } => {
+                write!(f, "{} is corrupt at offset {offset}: {reason}", path.display())
+            }
+            DbError::CorruptEntry { table_id, table, offset, source } => write!(
+                f,
+                "entry at offset {offset} could not be read by table {table} (id {table_id}): \
+                 {source}"
+            ),
+            DbError::UnknownTable { table_id, offset } => {
+                write!(f, "entry at offset {offset} belongs to table {table_id}, which the schema doesn't have")
+            }
+            DbError::OtherLogsExist { path, others } => {
+                let others: Vec<_> = others
+                    .iter()
+                    .map(|other| other.display().to_string())
+                    .collect();
+                write!(
+                    f,
+                    "refusing to create {} next to existing logs: {}",
+                    path.display(),
+                    others.join(", ")
+                )
+            }
+            DbError::MigrationPending { from, to } => write!(
+                f,
+                "the log of {from} has to be migrated to {to}, which this config doesn't allow"
+            ),
+            DbError::CompacterPanicked => write!(f, "a background compaction panicked"),
+            DbError::InitPanicked => write!(f, "opening the db panicked"),
+            DbError::TxPanicked => write!(f, "the transaction panicked"),
+            DbError::WriterGone => write!(f, "the db writer has stopped"),
+            DbError::SchemaNameMissing => {
+                write!(f, "schema name not populated, db-rs-derive should have done this")
+            }
+            DbError::SnapshotOutdated => {
+                write!(f, "log was compacted after this snapshot was taken")
+            }
+            DbError::TransactionInProgress => {
+                write!(f, "cannot snapshot while a transaction is in progress")
+            }
+            DbError::IndexOutOfBounds { index, len } => {
+                write!(f, "index {index} is out of bounds for a list of {len} elements")
+            }
+            DbError::QuotaExceeded { size, max } => {
+                write!(f, "write would grow the log to {size} bytes, the maximum is {max} bytes")
+            }
+            DbError::InsufficientSpace { available, required } => write!(
+                f,
+                "{available} bytes of free disk space available, compaction requires {required}"
+            ),
+            DbError::DiskFull { path } => {
+                write!(f, "ran out of disk space while writing {}", path.display())
+            }
+            DbError::LogPoisoned { cause } => write!(
+                f,
+                "the log stopped accepting writes after an io error ({cause}), recover or reopen \
+                 the db"
+            ),
         }
     }
 }
@@ -47,8 +339,31 @@ impl Error for DbError {
         match self {
             DbError::Io(e) => Some(e),
             DbError::Bincode(e) => Some(e),
-            DbError::MutexPoisoned => None,
-            DbError::Unexpected(_) => None,
+            DbError::CorruptEntry { source, .. } => Some(source.as_ref()),
+            DbError::MutexPoisoned
+            | DbError::Unexpected(_)
+            | DbError::EntryTooLarge { .. }
+            | DbError::WideTableId { .. }
+            | DbError::TableKindMismatch { .. }
+            | DbError::Locked { .. }
+            | DbError::LocksUnsupported
+            | DbError::UnsupportedVersion { .. }
+            | DbError::Corrupt { .. }
+            | DbError::UnknownTable { .. }
+            | DbError::OtherLogsExist { .. }
+            | DbError::MigrationPending { .. }
+            | DbError::CompacterPanicked
+            | DbError::InitPanicked
+            | DbError::TxPanicked
+            | DbError::WriterGone
+            | DbError::SchemaNameMissing
+            | DbError::SnapshotOutdated
+            | DbError::TransactionInProgress
+            | DbError::IndexOutOfBounds { .. }
+            | DbError::LogPoisoned { .. }
+            | DbError::QuotaExceeded { .. }
+            | DbError::InsufficientSpace { .. }
+            | DbError::DiskFull { .. } => None,
         }
     }
 }
@@ -48,6 +48,53 @@
 //! println!("{}", db.owner.data().unwrap());
 //! ```
 //!
+//! The log is named after the schema, `/tmp/test/SchemaV1.db` here. Renaming the struct would start a
+//! new, empty, db, so the name can be pinned with `#[schema(name = "...")]`, or overridden with
+//! [Config::file_stem]. [Config::guard_new_db] refuses to create a new db in a folder that already has
+//! logs of other names.
+//!
+//! Schemas can be generic, `struct Cache<K, V> { entries: LookupTable<K, V> }` works with any `K` and
+//! `V` its tables accept. All instances share the name `Cache`, so give each its own folder or
+//! [Config::file_stem]. Fields that shouldn't be persisted, like runtime handles, are marked with
+//! `#[schema(skip)]` and built with `Default` when the db is opened (and when it's snapshotted).
+//! [Db::recover] only reloads the tables, so skipped fields keep their values.
+//!
+//! A schema can be mounted inside another with `#[schema(nested)]`, so a module can ship its own
+//! schema and an app can compose them:
+//!
+//! ```ignore
+//! #[derive(Schema)]
+//! struct App {
+//!     users: LookupTable<Uuid, Account>,
+//!     #[schema(nested)]
+//!     files: files::Files,
+//! }
+//! ```
+//!
+//! The nested tables take the ids after the ones before them, and write to the same log: they're part
+//! of the app's transactions, snapshots and compactions. Options like `name` and `migrate_from` only
+//! apply when a schema is opened on its own.
+//!
+//! A schema has room for 254 tables, nested ones included. `#[schema(wide_table_ids)]` raises that to
+//! 65535 by giving the log wider table ids, see [Config::wide_table_ids]. Existing logs are upgraded
+//! the first time they're opened with it, after which older versions of db-rs can't read them.
+//!
+//! Tools that work over any schema, like admin screens or exports, can use `#[schema(reflect)]`. It
+//! implements [reflect::Reflect], which lists the tables of the schema with their ids and types, and
+//! visits each of them as a [reflect::TableView] that reports its length and serialized size, and with
+//! the `json` feature dumps its contents.
+//!
+//! A log can't be decoded without the types of its schema, `#[schema(describe)]` stores a
+//! [descriptor::SchemaDescriptor] in the header of the log with the name, id, kind and key and value
+//! types of every table. Compaction keeps it, and it's updated when the schema changes. Tools can read
+//! it with [descriptor::SchemaDescriptor::read] to label the entries of a log they have no types for.
+//!
+//! When the tables aren't known at compile time, for plugins and the like, [DynDb] registers them by
+//! name as they're opened, with `db.lookup::<K, V>("name")` and friends. Their ids and kinds are
+//! recorded in the log, opening a table as another kind of table, or with other types, than it was
+//! registered with is an error (`db.retype` records types that were moved or renamed), and
+//! transactions and compaction work as they do for a derived schema.
+//!
 //! ## Table Types
 //!
 //! Each table has an in-memory representation and a corresponding log entry format. For instance
@@ -61,6 +108,46 @@
 //! `HashSet` in question (see [lookup_list::LogEntry] or [lookup_set::LogEntry]).
 //!
 //!
+//! ## Schema Migrations
+//!
+//! Log entries are the bincode representation of your types, so changing the shape of a type makes
+//! the existing log unreadable. Instead, define the new version of your schema alongside the old one
+//! and describe how to get from one to the other:
+//!
+//! ```ignore
+//! #[derive(Schema)]
+//! #[schema(migrate_from = SchemaV1, with = v1_to_v2)]
+//! struct SchemaV2 {
+//!     owner: Single<Username>,
+//!     users: LookupTable<Username, AccountV2>,
+//! }
+//!
+//! fn v1_to_v2(old: &SchemaV1, new: &mut SchemaV2) -> DbResult<()> {
+//!     // read from old, write to new
+//! }
+//! ```
+//!
+//! When `SchemaV2::init` finds a log of `SchemaV1` and none of its own, it writes what `v1_to_v2`
+//! produces into a new log, and archives the old one. The new log is only put in place once it's
+//! complete, and records that it was migrated from `SchemaV1` so the migration never runs twice. See
+//! [migration::migrate] for the details. Migrations chain, `SchemaV3` can migrate from `SchemaV2`.
+//!
+//! When only the value type of a table changes, the table can list its older value types instead:
+//!
+//! ```ignore
+//! #[derive(Schema)]
+//! struct SchemaV1 {
+//!     #[table(upgrade = [AccountV1 -> AccountV2 -> Account])]
+//!     users: LookupTable<Username, Account>,
+//! }
+//! ```
+//!
+//! Entries that don't deserialize as the current type are tried as each older type, newest first, and
+//! converted up using `From` (`AccountV2: From<AccountV1>`, `Account: From<AccountV2>`). The next
+//! [Db::compact_log] rewrites them with the current type. This relies on bincode failing to read old
+//! entries as the new type, which is the case when fields are added, but not when they're removed
+//! from the end of a struct.
+//!
 //! ## Log Compaction
 //!
 //! At any point you can call [Db::compact_log] on your database. This will atomically write a
@@ -68,8 +155,41 @@
 //! LookupTable that was written to many times, the compact representation will only contain the
 //! last value. Each table type descibes it's own compact representation.
 //!
-//! If your database is in an `Arc<Mutex>>` you can additionally use the [BackgroundCompacter]
-//! which will perform compactions periodically in a separate thread.
+//! If your database is in an `Arc<Mutex>>` or a [ConcurrentDb] you can additionally use the
+//! [BackgroundCompacter] which will perform compactions periodically in a separate thread.
+//!
+//! Rather than compacting blindly, a [CompactionPolicy] can decide when compaction is worthwhile
+//! based on the log's size, how much it grew since the last compaction, or the number of writes
+//! since then. Policies can be checked inline with [Db::compact_if] or used by
+//! [BackgroundCompacter::begin_policy_compacter]. [Db::stats] breaks the log down further, reporting
+//! how many entries and bytes each table has in the log and how often it was written to since the
+//! last compaction.
+//!
+//! [BackgroundCompacter::spawn_compacter] returns a [CompacterHandle] which reports the outcome of
+//! every compaction, can trigger one immediately, and stops the compacter without waiting for its
+//! next scheduled run. Failed compactions are retried with a backoff configured in
+//! [CompacterConfig].
+//!
+//! [Config::max_log_size] caps the size of the log, writes that would grow it further fail with
+//! [DbError::QuotaExceeded] unless [Config::compact_on_quota] is set, in which case the log is
+//! compacted first. Compaction writes the new log next to the old one, [Config::min_free_space]
+//! makes it fail up front rather than partway through when the disk is nearly full. A compaction that
+//! fails removes its partially written log.
+//!
+//! ## Snapshots
+//!
+//! [Db::snapshot] returns a read-only [Snapshot] of every table as of the last committed transaction.
+//! Taking a snapshot is cheap: tables share their data with the snapshot, and only make a copy of it
+//! the first time they're written to afterwards, which is why the keys and values of tables must be
+//! `Clone`. Snapshots can be sent to other threads for long
+//! running reads, and [Snapshot::compact_log] compacts the log without blocking writers for the
+//! duration of the compaction.
+//!
+//! ## Sharing a DB between threads
+//!
+//! [ConcurrentDb] wraps your schema in an `Arc<RwLock<>>`. Any number of threads can read your tables
+//! at once, while [ConcurrentDb::transaction] gives one writer at a time exclusive access for the
+//! duration of a transaction. Readers never observe a partially applied transaction.
 //!
 //! ## TXs and Batch Writing
 //!
@@ -78,12 +198,20 @@
 //! transaction. TXs are also a mechanism for batch writing, log entries are kept in memory until
 //! the transaction completes and written once to disk.
 //!
+//! For bulk loads outside of transactions, [Config::flush_policy] can be set to
+//! [FlushPolicy::Buffered], which keeps writes in a reusable buffer until a size or time threshold
+//! is reached, [Db::flush] is called, or the db is dropped.
+//!
+//! If writing to the log fails, it stops accepting writes with [DbError::LogPoisoned], as entries
+//! appended after a partially written one couldn't be read back. [Db::recover] truncates the partial
+//! entry and reloads the tables from the log, after which writes are accepted again.
+//!
 //! ## Active areas of thought and research
 //!
 //! -   Because the db implementation (like redis) is single threaded, it forces you to achieve application throughput via low
 //!     latency rather than concurrency. Currently, this suits our needs. Simply being embedded gives us more than enough
 //!     throughput compared to something like Postgres. For use in a server-style setting put the database in
-//!     an `Arc<Mutex<>>`.
+//!     a [ConcurrentDb].
 //! -   The database offers no tools at the moment to define integrity constraints beyond what the Rust type system implicitly
 //!     enforces (non-null for instance). At the moment for us, this is simply an application side concern.
 //!
@@ -92,19 +220,44 @@
 //! `clone` - derive clone on all table types. Consistency between cloned database is not provided.
 //! Useful in testing situations.
 //!
+//! `tokio` - [AsyncDb], an async handle whose transactions are futures. A dedicated writer task
+//! group-commits transactions that arrive concurrently into a single write to the log, until
+//! `AsyncDb::close` stops it. Also provides
+//! [compacter::AsyncBackgroundCompacter], driven by a tokio interval.
+//!
+//! `json` - [reflect::TableView::dump], the contents of a table as json for schemas with
+//! `#[schema(reflect)]`.
+//!
+//! `tracing` - spans and events from [tracing](https://docs.rs/tracing) for opening the log,
+//! migration, file locking, replay (with per-table entry counts and time), transactions, writes and
+//! compaction. Errors are recorded on the span they occurred in.
+//!
 //! ## Used by
 //!
 //! -   [Lockbook](https://github.com/lockbook/lockbook)
 //!
 
+#[cfg(feature = "tokio")]
+pub use crate::async_db::AsyncDb;
 pub use crate::compacter::BackgroundCompacter;
 pub use crate::compacter::CancelSig;
+pub use crate::compacter::CompacterConfig;
+pub use crate::compacter::CompacterHandle;
+pub use crate::compacter::CompactionPolicy;
+pub use crate::concurrent::ConcurrentDb;
 pub use crate::config::Config;
+pub use crate::config::FlushPolicy;
 pub use crate::db::Db;
+pub use crate::db::Tables;
+pub use crate::dyn_db::DynDb;
 pub use crate::errors::DbError;
 pub use crate::errors::DbResult;
 pub use crate::logger::Logger;
 pub use crate::logger::TxHandle;
+pub use crate::snapshot::Snapshot;
+pub use crate::stats::DbStats;
+pub use crate::stats::LogStats;
+pub use crate::stats::TableStats;
 
 pub use crate::list::List;
 pub use crate::lookup::LookupTable;
@@ -112,17 +265,26 @@ pub use crate::lookup_list::LookupList;
 pub use crate::lookup_set::LookupSet;
 pub use crate::single::Single;
 
+#[cfg(feature = "tokio")]
+pub mod async_db;
 pub mod compacter;
+pub mod concurrent;
 pub mod config;
 pub mod db;
+pub mod descriptor;
+pub mod dyn_db;
 pub mod errors;
 pub mod list;
 pub mod logger;
 pub mod lookup;
 pub mod lookup_list;
 pub mod lookup_set;
+pub mod migration;
+pub mod reflect;
 pub mod single;
+pub mod snapshot;
+pub mod stats;
 pub mod table;
 
-pub type TableId = u8;
+pub type TableId = u16;
 pub type ByteCount = u32;
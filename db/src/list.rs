@@ -1,7 +1,12 @@
-use crate::table::Table;
-use crate::{DbResult, Logger, TableId};
+use crate::descriptor::TableKind;
+use crate::reflect::{self, TableView};
+use crate::table::{Table, Upgrade};
+use crate::{DbError, DbResult, Logger, TableId};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::io::Write;
+use std::sync::Arc;
 
 /// Table backed by a [Vec] of `T`
 #[derive(Debug)]
@@ -11,7 +16,7 @@ where
     T: Serialize + DeserializeOwned,
 {
     table_id: TableId,
-    inner: Vec<T>,
+    inner: Arc<Vec<T>>,
     pub logger: Logger,
 }
 
@@ -23,84 +28,162 @@ pub enum LogEntry<T> {
     Clear,
 }
 
+impl<T> LogEntry<T> {
+    fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> LogEntry<U> {
+        match self {
+            LogEntry::Push(t) => LogEntry::Push(f(t)),
+            LogEntry::Insert(idx, t) => LogEntry::Insert(idx, f(t)),
+            LogEntry::Remove(idx) => LogEntry::Remove(idx),
+            LogEntry::Clear => LogEntry::Clear,
+        }
+    }
+}
+
 impl<T> Table for List<T>
 where
-    T: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
 {
     fn init(table_id: TableId, logger: Logger) -> Self {
-        let inner = vec![];
-        Self { table_id, inner, logger }
+        Self { table_id, inner: Default::default(), logger }
     }
 
     fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()> {
-        match bincode::deserialize(bytes)? {
-            LogEntry::Insert(idx, element) => {
-                self.inner.insert(idx, element);
-            }
-            LogEntry::Remove(idx) => {
-                self.inner.remove(idx);
-            }
-            LogEntry::Push(el) => {
-                self.inner.push(el);
-            }
-            LogEntry::Clear => {
-                self.inner.clear();
-            }
-        };
-
-        Ok(())
+        self.apply(bincode::deserialize(bytes)?)
     }
 
     fn compact_repr(&self) -> DbResult<Vec<u8>> {
         let mut repr = vec![];
+        self.write_compact_repr(&mut repr)?;
+        Ok(repr)
+    }
 
-        for v in &self.inner {
-            let data = bincode::serialize(&LogEntry::Push(v))?;
-            let mut data = Logger::log_entry(self.table_id, data);
-            repr.append(&mut data);
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        let mut data = vec![];
+        for v in self.inner.iter() {
+            data.clear();
+            bincode::serialize_into(&mut data, &LogEntry::Push(v))?;
+            Logger::write_entry(out, self.table_id, &data)?;
         }
 
-        Ok(repr)
+        Ok(())
+    }
+
+    fn snapshot(&self, table_id: TableId, logger: Logger) -> DbResult<Self> {
+        Ok(Self { table_id, inner: self.inner.clone(), logger })
+    }
+
+    fn kind() -> TableKind {
+        TableKind::List { value: type_name::<T>().to_string() }
+    }
+}
+
+impl<T> Upgrade for List<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    type Value = T;
+
+    fn handle_old_event<Old, F>(&mut self, bytes: &[u8], upgrade: F) -> DbResult<()>
+    where
+        Old: DeserializeOwned,
+        F: FnMut(Old) -> T,
+    {
+        self.apply(bincode::deserialize::<LogEntry<Old>>(bytes)?.map(upgrade))
+    }
+}
+
+impl<T> TableView for List<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn serialized_size(&self) -> DbResult<u64> {
+        reflect::compact_size(self)
+    }
+
+    #[cfg(feature = "json")]
+    fn dump(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(&*self.inner)
     }
 }
 
 impl<T> List<T>
 where
-    T: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
 {
-    pub fn push(&mut self, t: T) -> DbResult<()> {
-        let log_entry = LogEntry::Push(&t);
-        let data = bincode::serialize(&log_entry)?;
-        self.inner.push(t);
+    fn apply(&mut self, entry: LogEntry<T>) -> DbResult<()> {
+        let inner = Arc::make_mut(&mut self.inner);
+        match entry {
+            LogEntry::Insert(idx, element) => {
+                if idx > inner.len() {
+                    return Err(DbError::IndexOutOfBounds { index: idx, len: inner.len() });
+                }
+                inner.insert(idx, element);
+            }
+            LogEntry::Remove(idx) => {
+                if idx >= inner.len() {
+                    return Err(DbError::IndexOutOfBounds { index: idx, len: inner.len() });
+                }
+                inner.remove(idx);
+            }
+            LogEntry::Push(el) => {
+                inner.push(el);
+            }
+            LogEntry::Clear => {
+                inner.clear();
+            }
+        };
 
+        Ok(())
+    }
+
+    pub fn push(&mut self, t: T) -> DbResult<()> {
+        let data = bincode::serialize(&LogEntry::Push(&t))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
+
+        inner.push(t);
         Ok(())
     }
 
     pub fn pop(&mut self) -> DbResult<Option<T>> {
-        let log_entry: LogEntry<T> = LogEntry::Remove(self.inner.len() - 1);
-        let data = bincode::serialize(&log_entry)?;
-        let result = self.inner.pop();
+        if self.inner.is_empty() {
+            return Ok(None);
+        }
 
+        let data = bincode::serialize(&LogEntry::<T>::Remove(self.inner.len() - 1))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(result)
+
+        Ok(inner.pop())
     }
 
+    /// Errors with [DbError::IndexOutOfBounds] rather than panicking if `index` is out of bounds.
     pub fn remove(&mut self, index: usize) -> DbResult<T> {
-        let log_entry: LogEntry<T> = LogEntry::Remove(index);
-        let data = bincode::serialize(&log_entry)?;
-        let result = self.inner.remove(index);
+        if index >= self.inner.len() {
+            return Err(DbError::IndexOutOfBounds { index, len: self.inner.len() });
+        }
 
+        let data = bincode::serialize(&LogEntry::<T>::Remove(index))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(result)
+
+        Ok(inner.remove(index))
     }
 
     pub fn clear(&mut self) -> DbResult<()> {
-        let log_entry: LogEntry<T> = LogEntry::Clear;
-        let data = bincode::serialize(&log_entry)?;
-        self.inner.clear();
+        if self.inner.is_empty() {
+            return Ok(());
+        }
 
+        let data = bincode::serialize(&LogEntry::<T>::Clear)?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
+
+        inner.clear();
         Ok(())
     }
 
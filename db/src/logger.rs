@@ -1,22 +1,226 @@
 use crate::config::Config;
+#[cfg(not(target_family = "wasm"))]
+use crate::config::FlushPolicy;
+use crate::descriptor::SchemaDescriptor;
 use crate::errors::DbResult;
+use crate::reflect::ByteCounter;
+use crate::stats::{DbStats, LogStats, TableStats};
 use crate::{ByteCount, DbError, TableId};
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_family = "wasm"))]
+use std::sync::Weak;
 use std::sync::{Arc, Mutex};
+#[cfg(not(target_family = "wasm"))]
+use std::thread;
+#[cfg(any(feature = "tracing", not(target_family = "wasm")))]
+use std::time::Duration;
+use std::time::Instant;
 
 #[cfg(not(target_family = "wasm"))]
 use fs2::FileExt;
 
+/// Entries whose size is this or larger have their real size written as a u64 after it.
+const EXTENDED_SIZE: ByteCount = ByteCount::MAX;
+
+/// The size of a [Header] with a one byte table id that uses [EXTENDED_SIZE], like that of a tx.
+const EXTENDED_SIZE_HEADER: usize = 13;
+
+/// Table ids this or larger are written as this byte followed by the real id as a u16.
+const WIDE_ID: u8 = u8::MAX;
+
+/// The size of a [Header] with a wide table id that uses [EXTENDED_SIZE], the largest a header gets.
+const MAX_HEADER: usize = 15;
+
+/// The bytes that precede every entry in the log: a table id followed by the size of the entry.
+#[derive(Debug, Copy, Clone)]
+pub struct Header {
+    bytes: [u8; MAX_HEADER],
+    len: usize,
+}
+
+impl Header {
+    fn new(id: TableId, size: u64) -> Self {
+        Self::build(id, size, false)
+    }
+
+    fn extended(id: TableId, size: u64) -> Self {
+        Self::build(id, size, true)
+    }
+
+    fn build(id: TableId, size: u64, extended: bool) -> Self {
+        let mut bytes = [0; MAX_HEADER];
+        let mut len = match u8::try_from(id) {
+            Ok(id) if id != WIDE_ID => {
+                bytes[0] = id;
+                1
+            }
+            _ => {
+                bytes[0] = WIDE_ID;
+                bytes[1..3].copy_from_slice(&id.to_be_bytes());
+                3
+            }
+        };
+
+        match ByteCount::try_from(size) {
+            Ok(size) if size != EXTENDED_SIZE && !extended => {
+                bytes[len..len + 4].copy_from_slice(&size.to_be_bytes());
+                len += 4;
+            }
+            _ => {
+                bytes[len..len + 4].copy_from_slice(&EXTENDED_SIZE.to_be_bytes());
+                bytes[len + 4..len + 12].copy_from_slice(&size.to_be_bytes());
+                len += 12;
+            }
+        }
+        Self { bytes, len }
+    }
+
+    /// The table id and entry size of the header at the start of `bytes`, in a log of
+    /// `log_version`, along with the length of the header. None if `bytes` ends before it does.
+    fn parse(log_version: u8, bytes: &[u8]) -> Option<(TableId, u64, usize)> {
+        let (id, mut len) = match *bytes.first()? {
+            WIDE_ID if log_version >= 4 => {
+                (TableId::from_be_bytes(bytes.get(1..3)?.try_into().unwrap()), 3)
+            }
+            id => (id as TableId, 1),
+        };
+
+        let mut size =
+            ByteCount::from_be_bytes(bytes.get(len..len + 4)?.try_into().unwrap()) as u64;
+        len += 4;
+        if log_version >= 2 && size == EXTENDED_SIZE as u64 {
+            size = u64::from_be_bytes(bytes.get(len..len + 8)?.try_into().unwrap());
+            len += 8;
+        }
+        Some((id, size, len))
+    }
+}
+
+impl Deref for Header {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.bytes[..self.len]
+    }
+}
+
 pub struct LogFormat<'a> {
     pub table_id: TableId,
     pub bytes: &'a [u8],
+    /// where the entry's frame starts in the log file
+    pub offset: u64,
+}
+
+/// Reports how replaying the log into each table went, used by the code `#[derive(Schema)]`
+/// generates. Does nothing unless the `tracing` feature is enabled.
+#[doc(hidden)]
+pub struct Replay {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    tables: BTreeMap<TableId, (u64, Duration)>,
+}
+
+impl Replay {
+    /// Start timing the replay, including reading and parsing the log.
+    pub fn start() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "replay",
+                entries = tracing::field::Empty,
+                time = tracing::field::Empty
+            ),
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Hand the entry at `offset` to the table with `id`, entries the table can't deserialize or
+    /// apply are reported as [DbError::CorruptEntry].
+    pub fn entry<F>(
+        &mut self, id: TableId, table: &'static str, offset: u64, handle: F,
+    ) -> DbResult<()>
+    where
+        F: FnOnce() -> DbResult<()>,
+    {
+        #[cfg(feature = "tracing")]
+        let start = Instant::now();
+
+        let result = handle().map_err(|err| err.in_entry(id, table, offset));
+
+        #[cfg(feature = "tracing")]
+        {
+            let stats = self.tables.entry(id).or_default();
+            stats.0 += 1;
+            stats.1 += start.elapsed();
+            if let Err(e) = &result {
+                tracing::error!(parent: &self.span, table, error = %e, "failed to replay entry");
+            }
+        }
+
+        result
+    }
+
+    /// Report the entries each table replayed, `names` maps table ids to field names.
+    #[allow(unused_variables)]
+    pub fn finish(self, names: &[(TableId, &str)]) {
+        #[cfg(feature = "tracing")]
+        {
+            let entries: u64 = self.tables.values().map(|(entries, _)| entries).sum();
+            self.span
+                .record("entries", entries)
+                .record("time", tracing::field::debug(self.start.elapsed()));
+        }
+        #[cfg(feature = "tracing")]
+        for (id, name) in names {
+            let (entries, time) = self.tables.get(id).copied().unwrap_or_default();
+            tracing::debug!(
+                parent: &self.span,
+                table = name,
+                id,
+                entries,
+                time = ?time,
+                "replayed table"
+            );
+        }
+    }
+}
+
+/// Compacts the log without access to the tables, see [crate::Db::compact_from_log].
+#[derive(Clone)]
+struct Compacter(Arc<CompactFn>);
+
+type CompactFn = dyn Fn(&Logger) -> DbResult<()> + Send + Sync;
+
+impl std::fmt::Debug for Compacter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Compacter")
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Logger {
     inner: Arc<Mutex<LoggerInner>>,
+    compaction: Arc<Mutex<()>>,
+}
+
+/// Where the log ended at a given moment, see [crate::Snapshot].
+#[derive(Debug, Clone)]
+pub(crate) struct LogPosition {
+    compactions: u64,
+    offset: u64,
+    writes: u64,
+    table_writes: BTreeMap<TableId, u64>,
 }
 
 #[derive(Debug)]
@@ -27,9 +231,23 @@ struct LoggerInner {
     incomplete_write: bool,
     current_txs: usize,
     tx_data: Option<Vec<u8>>,
+    compactions: u64,
+    write_buffer: Vec<u8>,
+    oldest_buffered_write: Option<Instant>,
+    log_size: u64,
+    compacted_size: u64,
+    writes: u64,
+    compaction_writes: u64,
+    last_compaction: Instant,
+    frames: FrameCounter,
+    table_writes: BTreeMap<TableId, u64>,
+    compaction_table_writes: BTreeMap<TableId, u64>,
+    failure: Option<String>,
+    compacter: Option<Compacter>,
 }
 
 impl Logger {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(path = %config.path.display(), no_io = config.no_io)))]
     pub fn init(config: Config) -> DbResult<Self> {
         if config.create_path {
             // todo: is this happening for no_io?
@@ -40,14 +258,20 @@ impl Logger {
             None
         } else {
             Self::handle_migration(&config)?;
+            Self::guard_new_db(&config)?;
             Some(Self::open_file(&config, &config.db_location_v2()?)?)
         };
+        let log_metadata = Self::read_or_stamp_metadata(&config, &mut file)?;
 
         let incomplete_write = false;
         let tx_data = None;
         let current_txs = 0;
+        let compactions = 0;
 
-        let log_metadata = Self::read_or_stamp_metadata(&config, &mut file)?;
+        let log_size = match &file {
+            Some(file) => file.metadata()?.len(),
+            None => 0,
+        };
 
         let inner = Arc::new(Mutex::new(LoggerInner {
             file,
@@ -56,16 +280,66 @@ impl Logger {
             tx_data,
             current_txs,
             log_metadata,
+            compactions,
+            write_buffer: vec![],
+            oldest_buffered_write: None,
+            log_size,
+            compacted_size: log_size,
+            writes: 0,
+            compaction_writes: 0,
+            last_compaction: Instant::now(),
+            frames: FrameCounter::default(),
+            table_writes: BTreeMap::new(),
+            compaction_table_writes: BTreeMap::new(),
+            failure: None,
+            compacter: None,
         }));
+        let compaction = Default::default();
+
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let locked = inner.lock()?;
+            if let (FlushPolicy::Buffered { max_delay: Some(max_delay), .. }, Some(_)) =
+                (&locked.config.flush_policy, &locked.file)
+            {
+                Self::spawn_flusher(Arc::downgrade(&inner), *max_delay);
+            }
+        }
+
+        Ok(Self { inner, compaction })
+    }
 
-        Ok(Self { inner })
+    /// Flush buffered writes once the oldest is `max_delay` old, even if no other write comes
+    /// along to notice. The thread stops once the log is dropped.
+    #[cfg(not(target_family = "wasm"))]
+    fn spawn_flusher(inner: Weak<Mutex<LoggerInner>>, max_delay: Duration) {
+        thread::spawn(move || loop {
+            let wait = {
+                let Some(inner) = inner.upgrade() else { return };
+                let Ok(mut inner) = inner.lock() else { return };
+                match inner.oldest_buffered_write {
+                    Some(oldest) if oldest.elapsed() >= max_delay => {
+                        // a failed flush poisons the log, which the next write reports
+                        if inner.check_healthy().is_ok() {
+                            let _ = inner.flush();
+                        }
+                        max_delay
+                    }
+                    Some(oldest) => max_delay.saturating_sub(oldest.elapsed()),
+                    None => max_delay,
+                }
+            };
+            thread::sleep(wait.max(Duration::from_millis(1)));
+        });
     }
 
     pub fn get_bytes(&self) -> DbResult<Vec<u8>> {
         let mut buffer: Vec<u8> = Vec::new();
 
         let mut inner = self.inner.lock()?;
+        let start = inner.metadata_len() as u64;
         if let Some(file) = inner.file.as_mut() {
+            file.seek(SeekFrom::Start(start))?;
             file.read_to_end(&mut buffer)?;
         }
 
@@ -73,27 +347,29 @@ impl Logger {
     }
 
     pub fn get_entries<'a>(&self, buffer: &'a [u8]) -> DbResult<Vec<LogFormat<'a>>> {
+        let mut inner = self.inner.lock()?;
+        let log_version = inner.log_version();
+        // the buffer starts after the metadata when it was read from a log file
+        let base = inner.metadata_len();
         let mut index = 0;
         let mut entries = vec![];
 
         while index < buffer.len() {
-            if buffer.len() < index + 4 + 1 {
-                self.inner.lock()?.incomplete_write = true;
-                return Ok(entries);
-            }
-
-            let table_id = buffer[index];
-            index += 1;
+            let (table_id, size, header) = match Header::parse(log_version, &buffer[index..]) {
+                Some(header) => header,
+                None => {
+                    inner.incomplete_write = true;
+                    return Ok(entries);
+                }
+            };
 
-            let size = ByteCount::from_be_bytes(
-                buffer[index..index + 4]
-                    .try_into()
-                    .expect("slice with incorrect length"),
-            ) as usize;
-            index += 4;
+            let offset = (base + index) as u64;
+            index += header;
 
-            if buffer.len() < index + size {
-                self.inner.lock()?.incomplete_write = true;
+            // a size that doesn't fit in memory can't be complete either
+            let size = usize::try_from(size).unwrap_or(usize::MAX);
+            if buffer.len() - index < size {
+                inner.incomplete_write = true;
                 return Ok(entries);
             }
 
@@ -102,15 +378,17 @@ impl Logger {
             }
 
             let bytes = &buffer[index..index + size];
-            entries.push(LogFormat { table_id, bytes });
+            inner.frames.entry(table_id, size as u64);
+            entries.push(LogFormat { table_id, bytes, offset });
             index += size;
         }
 
         Ok(entries)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err))]
     pub fn begin_tx(&self) -> DbResult<TxHandle> {
-        let h = TxHandle { inner: self.clone() };
+        let h = TxHandle { inner: self.clone(), ended: AtomicBool::new(false) };
         let mut inner = self.inner.lock()?;
         if inner.tx_data.is_none() {
             inner.tx_data = Some(vec![]);
@@ -119,6 +397,7 @@ impl Logger {
         Ok(h)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err))]
     pub fn end_tx(&self) -> DbResult<()> {
         let mut inner = self.inner.lock()?;
         if inner.current_txs == 0 {
@@ -127,84 +406,367 @@ impl Logger {
 
         inner.current_txs -= 1;
         if inner.current_txs == 0 {
-            let data = inner.tx_data.take();
-            drop(inner);
-            if let Some(data) = data {
-                self.write_to_file(Self::log_entry(0, data))?;
+            if let Some(data) = inner.tx_data.take() {
+                inner.check_healthy()?;
+                inner.check_size(data.len())?;
+                inner.append(0, &data)?;
+                #[cfg(feature = "tracing")]
+                tracing::trace!(size = data.len(), "committed tx");
             }
         }
 
         Ok(())
     }
 
-    pub fn write(&self, id: TableId, mut data: Vec<u8>) -> DbResult<()> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, data), err, fields(size = data.len())))]
+    pub fn write(&self, id: TableId, data: Vec<u8>) -> DbResult<()> {
         let mut inner = self.inner.lock()?;
         if inner.config.no_io {
             return Ok(());
         }
 
+        // nothing is recorded, not even in the tx, unless the entry made it into the log
+        inner.check_healthy()?;
+        inner.check_size(data.len())?;
+        inner.check_id(id)?;
+        let size = Self::header(id, &data).len() + data.len();
+        // a tx is written as a single entry, so it's refused now rather than once it's committed
+        if let Some(tx_data) = &inner.tx_data {
+            inner.check_size(tx_data.len() + size)?;
+        }
+        if let Err(err) = inner.check_quota(size) {
+            let compacter = match &inner.compacter {
+                Some(compacter) if inner.config.compact_on_quota => compacter.clone(),
+                _ => return Err(err),
+            };
+
+            drop(inner);
+            self.flush()?;
+            (compacter.0)(self)?;
+            inner = self.inner.lock()?;
+            inner.check_quota(size)?;
+        }
         if let Some(tx_data) = &mut inner.tx_data {
-            tx_data.extend(Self::header(id, &data));
-            tx_data.append(&mut data);
-            return Ok(());
+            Self::append_entry(tx_data, id, &data);
+        } else {
+            inner.append(id, &data)?;
         }
 
-        drop(inner);
+        inner.writes += 1;
+        *inner.table_writes.entry(id).or_default() += 1;
+        Ok(())
+    }
+
+    /// Record the size `tables` would compact the log to as its [LogStats::compacted_size], so
+    /// a log that was opened rather than compacted isn't taken to be all live data. Called by
+    /// `#[derive(Schema)]` once the tables are loaded.
+    #[doc(hidden)]
+    pub fn measure_live_data<F>(&self, tables: F) -> DbResult<()>
+    where
+        F: FnOnce(&mut dyn Write) -> DbResult<()>,
+    {
+        let mut size = ByteCounter(0);
+        tables(&mut size)?;
+        let mut inner = self.inner.lock()?;
+        // a compacted log holds its tables in a single tx, see Logger::write_compacted
+        let tx_header = Header::extended(0, size.0).len();
+        let live = (inner.metadata_len() + tx_header) as u64 + size.0;
+        inner.compacted_size = live.min(inner.log_size);
+        Ok(())
+    }
 
-        self.write_to_file(Self::log_entry(id, data))
+    /// Whether the metadata of the log is missing something the config asks for, like
+    /// [Config::wide_table_ids] or an up to date [Config::schema_descriptor]. Compacting the log
+    /// rewrites its metadata, which `#[derive(Schema)]` does when the db is opened.
+    #[doc(hidden)]
+    pub fn metadata_outdated(&self) -> DbResult<bool> {
+        let inner = self.inner.lock()?;
+        let meta = match &inner.log_metadata {
+            Some(meta) if !inner.config.read_only => meta,
+            _ => return Ok(false),
+        };
+        let mut upgraded = meta.clone();
+        upgraded.upgrade(&inner.config)?;
+        Ok(upgraded != *meta)
+    }
+
+    /// Write any buffered writes to the log file, see [crate::config::FlushPolicy].
+    pub fn flush(&self) -> DbResult<()> {
+        let mut inner = self.inner.lock()?;
+        inner.check_healthy()?;
+        inner.flush()
+    }
+
+    /// Register the function used to compact the log when a write exceeds
+    /// [Config::max_log_size], `#[derive(Schema)]` does this.
+    #[doc(hidden)]
+    pub fn set_compacter(&self, compacter: fn(&Logger) -> DbResult<()>) -> DbResult<()> {
+        self.set_compacter_with(compacter)
     }
 
-    fn write_to_file(&self, data: Vec<u8>) -> DbResult<()> {
+    /// [Logger::set_compacter] for a compacter with state, like [crate::DynDb]'s.
+    pub(crate) fn set_compacter_with<F>(&self, compacter: F) -> DbResult<()>
+    where
+        F: Fn(&Logger) -> DbResult<()> + Send + Sync + 'static,
+    {
+        self.inner.lock()?.compacter = Some(Compacter(Arc::new(compacter)));
+        Ok(())
+    }
+
+    /// Truncate the log to its last complete entry and accept writes again. After an io error the
+    /// log rejects writes with [DbError::LogPoisoned] until this is called (or the db is reopened),
+    /// as a partially written entry would make everything written after it unreadable. Buffered
+    /// writes and txs that failed to reach the log are lost, see [crate::Db::recover] to bring the
+    /// tables back in line with the log.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn recover(&self) -> DbResult<()> {
         let mut inner = self.inner.lock()?;
+        if inner.current_txs > 0 {
+            return Err(DbError::TransactionInProgress);
+        }
+        inner.write_buffer.clear();
+        inner.oldest_buffered_write = None;
+
+        let log_version = inner.log_version();
+        let start = inner.metadata_len();
         if let Some(file) = inner.file.as_mut() {
-            file.write_all(&data)?;
+            let mut buffer = vec![];
+            file.seek(SeekFrom::Start(start as u64))?;
+            file.read_to_end(&mut buffer)?;
+
+            let complete = (start + Self::complete_len(log_version, &buffer)) as u64;
+            if complete < file.metadata()?.len() {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(complete, "truncating partially written entry");
+                file.set_len(complete)?;
+            }
+            file.seek(SeekFrom::End(0))?;
+            inner.log_size = complete;
         }
+
+        // the tables are replayed from the truncated log, recounting its entries
+        inner.frames = FrameCounter::default();
+        inner.incomplete_write = false;
+        inner.failure = None;
         Ok(())
     }
 
-    pub fn header(id: TableId, data: &[u8]) -> [u8; 5] {
-        let size_info = (data.len() as ByteCount).to_be_bytes();
-        [id, size_info[0], size_info[1], size_info[2], size_info[3]]
+    /// The length of the frames at the start of `buffer` that were written completely.
+    fn complete_len(log_version: u8, buffer: &[u8]) -> usize {
+        let mut index = 0;
+        loop {
+            let rest = &buffer[index..];
+            let (_, size, header) = match Header::parse(log_version, rest) {
+                Some(header) => header,
+                None => return index,
+            };
+
+            match usize::try_from(size) {
+                Ok(size) if rest.len() - header >= size => index += header + size,
+                _ => return index,
+            }
+        }
+    }
+
+    pub fn header(id: TableId, data: &[u8]) -> Header {
+        Header::new(id, data.len() as u64)
+    }
+
+    /// Append a log entry to `buffer` without an intermediate allocation.
+    pub fn append_entry(buffer: &mut Vec<u8>, id: TableId, data: &[u8]) {
+        buffer.extend_from_slice(&Self::header(id, data));
+        buffer.extend_from_slice(data);
+    }
+
+    /// Write a log entry to `out`, used to stream [crate::table::Table::write_compact_repr].
+    pub fn write_entry(out: &mut dyn Write, id: TableId, data: &[u8]) -> DbResult<()> {
+        out.write_all(&Self::header(id, data))?;
+        out.write_all(data)?;
+        Ok(())
     }
 
     pub fn log_entry(id: TableId, mut data: Vec<u8>) -> Vec<u8> {
         let header = Self::header(id, &data);
         data.reserve(header.len());
-        data.splice(0..0, header);
+        data.splice(0..0, header.iter().copied());
         data
     }
 
-    pub fn compact_log(&self, data: Vec<u8>) -> DbResult<()> {
-        let mut inner = self.inner.lock()?;
+    /// Atomically replace the log with the compact representation of the tables, which `tables`
+    /// writes into the new log.
+    pub fn compact_log<F>(&self, tables: F) -> DbResult<()>
+    where
+        F: FnOnce(&mut dyn Write) -> DbResult<()>,
+    {
+        let position = self.inner.lock()?.position()?;
+        self.compact_log_since(tables, position)
+    }
+
+    /// Replace the log with the compacted `tables`, followed by anything that was written after
+    /// `since`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(compacted_size, carried_over))
+    )]
+    pub(crate) fn compact_log_since<F>(&self, tables: F, since: LogPosition) -> DbResult<()>
+    where
+        F: FnOnce(&mut dyn Write) -> DbResult<()>,
+    {
+        let _compacting = self.compaction.lock()?;
+
+        let inner = self.inner.lock()?;
         if inner.config.no_io {
             return Ok(());
         }
+        inner.check_healthy()?;
 
-        let temp_path = inner.config.compaction_location()?;
-        let final_path = inner.config.db_location_v2()?;
+        let config = inner.config.clone();
+        let temp_path = config.compaction_location()?;
+        let final_path = config.db_location_v2()?;
+        let mut log_meta = inner.log_metadata.clone().ok_or_else(|| DbError::Corrupt {
+            path: final_path.clone(),
+            offset: 0,
+            reason: "log metadata was never read",
+        })?;
+        drop(inner);
 
-        let mut file = Self::open_file(&inner.config, &temp_path)?;
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(required) = config.min_free_space {
+            let available = fs2::available_space(&config.path)?;
+            if available < required {
+                return Err(DbError::InsufficientSpace { available, required });
+            }
+        }
 
-        // write compaction count for future IPC reasons
-        let mut log_meta = inner
-            .log_metadata
-            .ok_or(DbError::Unexpected("log meta missing -- no_io == false"))?;
-        log_meta.compaction_count += 1;
-        let metadata_bytes = log_meta.to_bytes();
-        file.write_all(&metadata_bytes)?;
+        let mut file = Self::open_compaction_file(&config, &temp_path)?;
+        let (compacted_size, mut frames) =
+            match Self::write_compacted(&mut file, &mut log_meta, &config, tables) {
+                Ok(compacted) => compacted,
+                Err(err) => return Err(Self::discard_compaction(file, &temp_path, err)),
+            };
 
-        // write compacted data to a temporary file
-        let compacted_data = Self::log_entry(0, data);
-        file.write_all(&compacted_data)?;
+        // carry over writes that happened while we were compacting
+        let mut inner = self.inner.lock()?;
+        let carried_over = inner.flush().and_then(|_| {
+            if inner.compactions != since.compactions {
+                return Err(DbError::SnapshotOutdated);
+            }
+            let mut out = CountingWriter::new(&mut file, log_meta.log_version);
+            if let Some(old) = inner.file.as_mut() {
+                old.seek(SeekFrom::Start(since.offset))?;
+                io::copy(old, &mut out)?;
+            }
+            frames.merge(out.frames);
+            Ok(out.count)
+        });
+        let carried_over = match carried_over {
+            Ok(carried_over) => carried_over,
+            Err(err) => return Err(Self::discard_compaction(file, &temp_path, err)),
+        };
 
         // atomically make this the new log
         fs::rename(temp_path, final_path)?;
         inner.file = Some(file);
         inner.log_metadata = Some(log_meta);
+        inner.compactions += 1;
+        inner.compacted_size = compacted_size;
+        inner.log_size = compacted_size + carried_over;
+        inner.compaction_writes = since.writes;
+        inner.compaction_table_writes = since.table_writes;
+        inner.last_compaction = Instant::now();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("compacted_size", compacted_size)
+            .record("carried_over", carried_over);
+        // a tx that's still open is counted once it's appended to the new log
+        inner.frames = frames;
 
         Ok(())
     }
 
+    /// Write the metadata and the compacted `tables` into a new log, returning the size of the
+    /// log and the entries that were written.
+    fn write_compacted<F>(
+        file: &mut File, log_meta: &mut LogMetadata, config: &Config, tables: F,
+    ) -> DbResult<(u64, FrameCounter)>
+    where
+        F: FnOnce(&mut dyn Write) -> DbResult<()>,
+    {
+        // write compaction count for future IPC reasons, the new log is never in the v1 format
+        log_meta.compaction_count = log_meta.compaction_count.wrapping_add(1);
+        log_meta.upgrade(config)?;
+        let metadata_bytes = log_meta.to_bytes();
+        file.write_all(&metadata_bytes)?;
+
+        // stream the compacted tables into a tx whose size is filled in afterwards, the extended
+        // header is always used as the size isn't known upfront
+        let tx_start = metadata_bytes.len() as u64;
+        file.write_all(&Header::extended(0, 0))?;
+        let (count, frames) = {
+            let mut out = CountingWriter::new(BufWriter::new(&mut *file), log_meta.log_version);
+            tables(&mut out)?;
+            out.inner.flush()?;
+            (out.count, out.frames)
+        };
+
+        file.seek(SeekFrom::Start(tx_start))?;
+        file.write_all(&Header::extended(0, count))?;
+        let compacted_size = file.seek(SeekFrom::End(0))?;
+        Ok((compacted_size, frames))
+    }
+
+    /// Remove a compaction that failed partway, so it doesn't hold on to disk space.
+    fn discard_compaction(file: File, temp_path: &Path, err: DbError) -> DbError {
+        drop(file);
+        if let Err(_e) = fs::remove_file(temp_path) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_e, "failed to remove partial compaction");
+        }
+        err.disk_full_at(temp_path)
+    }
+
+    /// Read the metadata of the log `config` points to, None if there's no log there yet.
+    pub(crate) fn read_metadata(config: &Config) -> DbResult<Option<LogMetadata>> {
+        let path = config.db_location_v2()?;
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        LogMetadata::read(&mut file, &path)
+    }
+
+    /// Create the log `config` points to from the compacted `tables` of a migration from the
+    /// schema named `from`. The log only appears once it's been written completely.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(config, tables), err))]
+    pub(crate) fn create_migrated<F>(config: &Config, from: &str, tables: F) -> DbResult<()>
+    where
+        F: FnOnce(&mut dyn Write) -> DbResult<()>,
+    {
+        let temp_path = config.migration_location()?;
+        let mut log_meta = LogMetadata::default();
+        log_meta.set_migrated_from(from);
+
+        let mut file = Self::open_compaction_file(config, &temp_path)?;
+        if let Err(err) = Self::write_compacted(&mut file, &mut log_meta, config, tables) {
+            return Err(Self::discard_compaction(file, &temp_path, err));
+        }
+        drop(file);
+
+        fs::rename(temp_path, config.db_location_v2()?)?;
+        Ok(())
+    }
+
+    /// The current end of the log, errors if a tx is in progress as its writes haven't been logged
+    pub(crate) fn position(&self) -> DbResult<LogPosition> {
+        let mut inner = self.inner.lock()?;
+        if inner.current_txs > 0 {
+            return Err(DbError::TransactionInProgress);
+        }
+        inner.position()
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
     fn handle_migration(config: &Config) -> DbResult<()> {
         let v1 = config.db_location_v1()?;
         let v2 = config.db_location_v2()?;
@@ -222,43 +784,123 @@ impl Logger {
             return Ok(());
         }
 
+        // the entries predate extended sizes, so they're framed as v1 until the log is compacted
         let v1_bytes = fs::read(&v1)?;
-        let mut v2_bytes = LogMetadata::default().to_bytes().to_vec();
+        let mut v2_bytes = LogMetadata { log_version: 1, ..Default::default() }.to_bytes();
         v2_bytes.extend(v1_bytes);
         fs::write(&v2_temp, v2_bytes)?;
         fs::rename(v2_temp, v2)?;
         fs::remove_file(v1)?;
+        #[cfg(feature = "tracing")]
+        tracing::info!("migrated log to the v2 location");
+
+        Ok(())
+    }
+
+    /// With [Config::guard_new_db], refuse to create a log when the folder has logs of other names.
+    fn guard_new_db(config: &Config) -> DbResult<()> {
+        let path = config.db_location_v2()?;
+        if !config.guard_new_db || !config.create_db || config.read_only || path.exists() {
+            return Ok(());
+        }
+
+        let mut others = vec![];
+        for entry in fs::read_dir(&config.path)? {
+            let other = entry?.path();
+            if other.extension().map(|ext| ext == "db").unwrap_or(false) && other.is_file() {
+                others.push(other);
+            }
+        }
+        if !others.is_empty() {
+            others.sort();
+            return Err(DbError::OtherLogsExist { path, others });
+        }
 
         Ok(())
     }
 
-    fn open_file(config: &Config, db_location: &Path) -> DbResult<File> {
+    fn open_file(config: &Config, location: &Path) -> DbResult<File> {
+        // a read only log is never created, and creating a file without write access is an error
         let file = OpenOptions::new()
             .read(true)
-            .create(config.create_db || config.read_only)
+            .create(config.create_db && !config.read_only)
             .append(!config.read_only)
-            .open(db_location)?;
+            .open(location)?;
+
+        Self::lock_file(config, location, &file)?;
+
+        Ok(file)
+    }
 
+    /// Unlike [Logger::open_file] this isn't opened in append mode, so the size of the compacted
+    /// data can be written before it once it's known. Writes continue at the end of the file.
+    fn open_compaction_file(config: &Config, location: &Path) -> DbResult<File> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(location)?;
+
+        Self::lock_file(config, location, &file)?;
+
+        Ok(file)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err, fields(blocking = config.fs_locks_block)))]
+    fn lock_file(config: &Config, location: &Path, file: &File) -> DbResult<()> {
         #[cfg(not(target_family = "wasm"))]
         if config.fs_locks {
             if config.fs_locks_block {
                 file.lock_exclusive()?;
-            } else {
-                file.try_lock_exclusive()?;
+            } else if let Err(err) = file.try_lock_exclusive() {
+                if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                    return Err(DbError::Locked { path: location.to_path_buf() });
+                }
+                return Err(err.into());
             }
         }
         #[cfg(target_family = "wasm")]
         if config.fs_locks {
-            return Err(DbError::Unexpected("File Locks are not supported on wasm"));
+            let _ = (location, file);
+            return Err(DbError::LocksUnsupported);
         }
 
-        Ok(file)
+        Ok(())
     }
 
     pub(crate) fn config(&self) -> DbResult<Config> {
         Ok(self.inner.lock()?.config.clone())
     }
 
+    pub(crate) fn log_stats(&self) -> DbResult<LogStats> {
+        Ok(self.inner.lock()?.stats())
+    }
+
+    pub(crate) fn stats(&self) -> DbResult<DbStats> {
+        let inner = self.inner.lock()?;
+        let mut tables = inner.frames.tables.clone();
+        for (id, writes) in &inner.table_writes {
+            let since = inner
+                .compaction_table_writes
+                .get(id)
+                .copied()
+                .unwrap_or_default();
+            tables.entry(*id).or_default().writes_since_compaction = writes - since;
+        }
+
+        Ok(DbStats {
+            log: inner.stats(),
+            generation: inner
+                .log_metadata
+                .as_ref()
+                .map(|meta| meta.compaction_count)
+                .unwrap_or_default(),
+            tables,
+            failure: inner.failure.clone(),
+        })
+    }
+
     pub(crate) fn incomplete_write(&self) -> DbResult<bool> {
         Ok(self.inner.lock()?.incomplete_write)
     }
@@ -267,72 +909,340 @@ impl Logger {
         config: &Config, file: &mut Option<File>,
     ) -> DbResult<Option<LogMetadata>> {
         match file {
-            Some(file) => {
-                let mut buffer = [0_u8; 2];
-                let bytes_read = file.read(&mut buffer)?;
-                let mut needs_stamp = false;
-                match bytes_read {
-                    0 => {
-                        needs_stamp = true;
-                        buffer = LogMetadata::default().to_bytes();
-                    }
-                    2 => {}
-                    _ => {
-                        return Err(DbError::Unexpected(
-                            "Unexpected amount of bytes read from log stamp",
-                        ))
+            Some(file) => match LogMetadata::read(file, &config.db_location_v2()?)? {
+                Some(meta) => Ok(Some(meta)),
+                None => {
+                    let mut meta = LogMetadata::default();
+                    meta.upgrade(config)?;
+                    if !config.read_only {
+                        file.write_all(&meta.to_bytes())?;
                     }
-                };
-
-                if !config.read_only && needs_stamp {
-                    file.write_all(&buffer)?;
+                    Ok(Some(meta))
                 }
-                let meta = LogMetadata::from_bytes(buffer);
-                if meta.log_version != 1 {
-                    return Err(DbError::Unexpected("unexpected log format version found"));
-                }
-
-                Ok(Some(meta))
-            }
+            },
             None => Ok(None),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LogMetadata {
     /// knowing the log version that we're reading allows us to evolve the format and make breaking
     /// changes. At the very least, allows us to return an error in the event of a version mismatch
     /// (leaving the migration up to the client)
+    ///
+    /// 1: entries are framed by a table id and a [ByteCount] size
+    /// 2: a size of [ByteCount::MAX] is followed by the real size as a u64
+    /// 3: the metadata is followed by the [ByteCount] size of its properties, and the properties
+    /// 4: a table id of [WIDE_ID] is followed by the real id as a u16, see [Config::wide_table_ids]
     log_version: u8,
 
     /// compaction count is going to be a key data point to read when there are multiple processes
     /// reading and operating on the same log
     compaction_count: u8,
+
+    /// facts about the log that readers need to know about, framed like entries but with a
+    /// property tag in place of the table id. Properties a reader doesn't know are kept as is.
+    properties: BTreeMap<u8, Vec<u8>>,
 }
 
 impl Default for LogMetadata {
     fn default() -> Self {
-        Self { log_version: 1, compaction_count: 0 }
+        // logs without properties stay readable by versions of db-rs that predate them
+        Self { log_version: 2, compaction_count: 0, properties: BTreeMap::new() }
     }
 }
 
 impl LogMetadata {
-    fn to_bytes(self) -> [u8; 2] {
-        [self.log_version, self.compaction_count]
+    const LATEST_VERSION: u8 = 4;
+
+    /// the name of the schema whose log was migrated into this one, see [crate::migration]
+    const MIGRATED_FROM: u8 = 1;
+
+    /// the [SchemaDescriptor] of the schema that wrote the log, see [crate::descriptor]
+    const SCHEMA_DESCRIPTOR: u8 = 2;
+
+    /// Read the metadata at the start of the log file at `path`, None if the log is empty.
+    fn read<R: Read>(file: &mut R, path: &Path) -> DbResult<Option<Self>> {
+        let corrupt =
+            |offset, reason| DbError::Corrupt { path: path.to_path_buf(), offset, reason };
+
+        let mut buffer = [0_u8; 2];
+        match file.read(&mut buffer)? {
+            0 => return Ok(None),
+            2 => {}
+            _ => return Err(corrupt(0, "log metadata is truncated")),
+        };
+
+        let mut meta = Self {
+            log_version: buffer[0],
+            compaction_count: buffer[1],
+            properties: BTreeMap::new(),
+        };
+        if meta.log_version == 0 || meta.log_version > Self::LATEST_VERSION {
+            return Err(DbError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                expected: Self::LATEST_VERSION,
+                found: meta.log_version,
+            });
+        }
+        if meta.log_version < 3 {
+            return Ok(Some(meta));
+        }
+
+        let mut size = [0_u8; 4];
+        file.read_exact(&mut size)
+            .map_err(|_| corrupt(0, "log metadata is truncated"))?;
+        let size = ByteCount::from_be_bytes(size) as u64;
+        let mut properties = vec![];
+        file.take(size).read_to_end(&mut properties)?;
+        if properties.len() as u64 != size {
+            return Err(corrupt(0, "log metadata is truncated"));
+        }
+
+        let mut index = 0;
+        while index < properties.len() {
+            let offset = (6 + index) as u64;
+            if properties.len() - index < 5 {
+                return Err(corrupt(offset, "log property is truncated"));
+            }
+            let tag = properties[index];
+            let size =
+                ByteCount::from_be_bytes(properties[index + 1..index + 5].try_into().unwrap());
+            index += 5;
+
+            match usize::try_from(size) {
+                Ok(size) if properties.len() - index >= size => {
+                    meta.properties
+                        .insert(tag, properties[index..index + size].to_vec());
+                    index += size;
+                }
+                _ => return Err(corrupt(offset, "log property is truncated")),
+            }
+        }
+
+        Ok(Some(meta))
+    }
+
+    /// Bring the metadata in line with `config` before it's written to a new log, in the oldest
+    /// version that can hold it. Logs with wide table ids keep them, as their entries may use them.
+    fn upgrade(&mut self, config: &Config) -> DbResult<()> {
+        if let Some(descriptor) = &config.schema_descriptor {
+            self.properties
+                .insert(Self::SCHEMA_DESCRIPTOR, bincode::serialize(descriptor)?);
+        }
+
+        self.log_version = if config.wide_table_ids || self.log_version >= 4 {
+            4
+        } else if !self.properties.is_empty() {
+            3
+        } else {
+            2
+        };
+        Ok(())
+    }
+
+    /// the largest entry that can be written to a log of this version
+    fn max_entry_size(&self) -> u64 {
+        if self.log_version >= 2 {
+            u64::MAX
+        } else {
+            // v2 readers would interpret ByteCount::MAX as an extended size
+            EXTENDED_SIZE as u64 - 1
+        }
+    }
+
+    pub(crate) fn migrated_from(&self) -> Option<&str> {
+        self.properties
+            .get(&Self::MIGRATED_FROM)
+            .and_then(|name| std::str::from_utf8(name).ok())
+    }
+
+    pub(crate) fn schema_descriptor(&self) -> DbResult<Option<SchemaDescriptor>> {
+        match self.properties.get(&Self::SCHEMA_DESCRIPTOR) {
+            Some(descriptor) => Ok(Some(bincode::deserialize(descriptor)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn set_migrated_from(&mut self, name: &str) {
+        self.properties
+            .insert(Self::MIGRATED_FROM, name.as_bytes().to_vec());
+    }
+
+    /// the size of the metadata in the log, entries start right after it
+    fn len(&self) -> usize {
+        if self.log_version < 3 {
+            return 2;
+        }
+        let properties: usize = self.properties.values().map(|value| 5 + value.len()).sum();
+        2 + 4 + properties
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len());
+        bytes.extend([self.log_version, self.compaction_count]);
+        if self.log_version >= 3 {
+            let size = self.len() - 6;
+            bytes.extend((size as ByteCount).to_be_bytes());
+            for (tag, value) in &self.properties {
+                bytes.push(*tag);
+                bytes.extend((value.len() as ByteCount).to_be_bytes());
+                bytes.extend(value);
+            }
+        }
+        bytes
+    }
+}
+
+impl LoggerInner {
+    /// the version entries are framed in, the latest one when there's no log file, as entries
+    /// then only exist in memory, framed by [Header::new] as the latest version frames them
+    fn log_version(&self) -> u8 {
+        match &self.log_metadata {
+            Some(meta) => meta.log_version,
+            None => LogMetadata::LATEST_VERSION,
+        }
+    }
+
+    /// where the entries of the log start, 0 when there's no log file
+    fn metadata_len(&self) -> usize {
+        self.log_metadata
+            .as_ref()
+            .map(LogMetadata::len)
+            .unwrap_or_default()
+    }
+
+    fn check_healthy(&self) -> DbResult<()> {
+        match &self.failure {
+            Some(cause) => Err(DbError::LogPoisoned { cause: cause.clone() }),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `size` more bytes fit in the log, counting the tx being built (and its header).
+    fn check_quota(&self, size: usize) -> DbResult<()> {
+        let max = match self.config.max_log_size {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+
+        let pending = match &self.tx_data {
+            Some(tx_data) => tx_data.len() + EXTENDED_SIZE_HEADER,
+            None => 0,
+        };
+        let size = self.log_size + (pending + size) as u64;
+        if size > max {
+            return Err(DbError::QuotaExceeded { size, max });
+        }
+        Ok(())
+    }
+
+    /// Whether the log can frame entries of the table `id`.
+    fn check_id(&self, id: TableId) -> DbResult<()> {
+        if id >= WIDE_ID as TableId && self.log_version() < 4 {
+            return Err(DbError::WideTableId { id });
+        }
+        Ok(())
+    }
+
+    fn check_size(&self, size: usize) -> DbResult<()> {
+        let max = self
+            .log_metadata
+            .as_ref()
+            .map(LogMetadata::max_entry_size)
+            .unwrap_or(u64::MAX);
+        if size as u64 > max {
+            return Err(DbError::EntryTooLarge { size: size as u64, max });
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, id: TableId, data: &[u8]) -> DbResult<()> {
+        let buffered = self.write_buffer.len();
+        Logger::append_entry(&mut self.write_buffer, id, data);
+        let appended = (self.write_buffer.len() - buffered) as u64;
+        let oldest_write = *self.oldest_buffered_write.get_or_insert_with(Instant::now);
+
+        if self
+            .config
+            .flush_policy
+            .should_flush(self.write_buffer.len(), oldest_write)
+        {
+            self.flush()?;
+        }
+
+        self.log_size += appended;
+        if id == 0 {
+            let log_version = self.log_version();
+            self.frames.count(log_version, data);
+        } else {
+            self.frames.entry(id, data.len() as u64);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> DbResult<()> {
+        self.oldest_buffered_write = None;
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+
+        let result = match self.file.as_mut() {
+            Some(file) => file.write_all(&self.write_buffer),
+            None => Ok(()),
+        };
+        // the buffer is kept around to avoid reallocating it
+        self.write_buffer.clear();
+
+        // part of the buffer may have been written, anything appended after it would be lost
+        if let Err(err) = &result {
+            self.failure = Some(err.to_string());
+        }
+
+        Ok(result?)
+    }
+
+    fn position(&mut self) -> DbResult<LogPosition> {
+        self.flush()?;
+        let offset = match &self.file {
+            Some(file) => file.metadata()?.len(),
+            None => 0,
+        };
+        Ok(LogPosition {
+            compactions: self.compactions,
+            offset,
+            writes: self.writes,
+            table_writes: self.table_writes.clone(),
+        })
     }
 
-    fn from_bytes(bytes: [u8; 2]) -> Self {
-        Self { log_version: bytes[0], compaction_count: bytes[1] }
+    fn stats(&self) -> LogStats {
+        LogStats {
+            log_size: self.log_size,
+            compacted_size: self.compacted_size,
+            writes_since_compaction: self.writes - self.compaction_writes,
+            since_compaction: self.last_compaction.elapsed(),
+        }
     }
 }
 
 impl Drop for LoggerInner {
     fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %e, "failed to flush buffered writes");
+            #[cfg(not(feature = "tracing"))]
+            eprintln!("failed to flush buffered writes: {:?}", e);
+        }
+
         if let Some(file) = &self.file {
             if self.config.fs_locks {
                 #[cfg(not(target_family = "wasm"))]
                 if let Err(e) = fs2::FileExt::unlock(file) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(error = %e, "failed to unlock log lock");
+                    #[cfg(not(feature = "tracing"))]
                     eprintln!("failed to unlock log lock: {:?}", e);
                 }
             }
@@ -340,20 +1250,109 @@ impl Drop for LoggerInner {
     }
 }
 
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+    /// the version of the log being written, which decides how its frames are read
+    log_version: u8,
+    frames: FrameCounter,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W, log_version: u8) -> Self {
+        Self { inner, count: 0, log_version, frames: FrameCounter::default() }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        self.frames.count(self.log_version, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Tallies the entries of each table in a stream of log frames, which may arrive in arbitrary
+/// pieces. Like replay, it looks inside of txs rather than counting them as entries.
+#[derive(Debug, Default)]
+struct FrameCounter {
+    header: Vec<u8>,
+    remaining: u64,
+    tables: BTreeMap<TableId, TableStats>,
+}
+
+impl FrameCounter {
+    fn count(&mut self, log_version: u8, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            if self.remaining > 0 {
+                let skipped = buf
+                    .len()
+                    .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+                self.remaining -= skipped as u64;
+                buf = &buf[skipped..];
+                continue;
+            }
+
+            self.header.push(buf[0]);
+            buf = &buf[1..];
+            let (id, size, _) = match Header::parse(log_version, &self.header) {
+                Some(header) => header,
+                None => continue,
+            };
+
+            self.header.clear();
+            if id != 0 {
+                self.entry(id, size);
+                self.remaining = size;
+            }
+        }
+    }
+
+    fn entry(&mut self, id: TableId, size: u64) {
+        let table = self.tables.entry(id).or_default();
+        table.entries += 1;
+        table.bytes += size;
+    }
+
+    fn merge(&mut self, other: FrameCounter) {
+        for (id, stats) in other.tables {
+            let table = self.tables.entry(id).or_default();
+            table.entries += stats.entries;
+            table.bytes += stats.bytes;
+        }
+    }
+}
+
 #[must_use = "DB stays in Tx mode while this value is in scope. Manually call drop_safely() to handle io errors that may arise when tx terminates."]
 pub struct TxHandle {
     inner: Logger,
+    ended: AtomicBool,
 }
 
 impl TxHandle {
     pub fn drop_safely(&self) -> DbResult<()> {
+        // a handle only ever ends its own tx, so the eventual Drop doesn't end an outer one
+        if self.ended.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
         self.inner.end_tx()
     }
 }
 
 impl Drop for TxHandle {
+    /// Ends the tx, errors are only logged as panicking here could abort the process. A tx that
+    /// failed to reach the log leaves it poisoned, so the next write reports it.
     fn drop(&mut self) {
-        self.drop_safely()
-            .expect("auto tx-end panicked. Call drop_safely() for non-panicking variant.");
+        if let Err(e) = self.drop_safely() {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %e, "failed to end tx, call drop_safely() to handle this error");
+            #[cfg(not(feature = "tracing"))]
+            eprintln!("failed to end tx, call drop_safely() to handle this error: {:?}", e);
+        }
     }
 }
@@ -1,11 +1,16 @@
+use crate::descriptor::TableKind;
 use crate::errors::DbResult;
 use crate::logger::Logger;
-use crate::table::Table;
+use crate::reflect::{self, TableView};
+use crate::table::{Table, Upgrade};
 use crate::TableId;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::any::type_name;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::io::Write;
+use std::sync::Arc;
 
 /// A table backed by a [HashMap] of type `K`, `V`
 #[derive(Debug)]
@@ -16,7 +21,7 @@ where
     V: Serialize,
 {
     table_id: TableId,
-    inner: HashMap<K, V>,
+    inner: Arc<HashMap<K, V>>,
     pub logger: Logger,
 }
 
@@ -27,64 +32,133 @@ pub enum LogEntry<K, V> {
     Clear,
 }
 
+impl<K, V> LogEntry<K, V> {
+    fn map<W, F: FnMut(V) -> W>(self, mut f: F) -> LogEntry<K, W> {
+        match self {
+            LogEntry::Insert(k, v) => LogEntry::Insert(k, f(v)),
+            LogEntry::Remove(k) => LogEntry::Remove(k),
+            LogEntry::Clear => LogEntry::Clear,
+        }
+    }
+}
+
 impl<K, V> Table for LookupTable<K, V>
 where
-    K: Hash + Eq + Serialize + DeserializeOwned,
-    V: Serialize + DeserializeOwned,
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone,
 {
     fn init(table_id: TableId, logger: Logger) -> Self {
-        Self { table_id, inner: HashMap::default(), logger }
+        Self { table_id, inner: Default::default(), logger }
     }
 
     fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()> {
-        match bincode::deserialize(bytes)? {
-            LogEntry::Insert(k, v) => {
-                self.inner.insert(k, v);
-            }
-            LogEntry::Remove(k) => {
-                self.inner.remove(&k);
-            }
-            LogEntry::Clear => {
-                self.inner.clear();
-            }
-        };
-
-        Ok(())
+        self.apply(bincode::deserialize(bytes)?)
     }
 
     fn compact_repr(&self) -> DbResult<Vec<u8>> {
         let mut repr = vec![];
-        for (k, v) in &self.inner {
-            let data = bincode::serialize(&LogEntry::Insert(k, v))?;
-            let mut data = Logger::log_entry(self.table_id, data);
-            repr.append(&mut data);
+        self.write_compact_repr(&mut repr)?;
+        Ok(repr)
+    }
+
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        let mut data = vec![];
+        for (k, v) in self.inner.iter() {
+            data.clear();
+            bincode::serialize_into(&mut data, &LogEntry::Insert(k, v))?;
+            Logger::write_entry(out, self.table_id, &data)?;
         }
 
-        Ok(repr)
+        Ok(())
+    }
+
+    fn snapshot(&self, table_id: TableId, logger: Logger) -> DbResult<Self> {
+        Ok(Self { table_id, inner: self.inner.clone(), logger })
+    }
+
+    fn kind() -> TableKind {
+        TableKind::LookupTable {
+            key: type_name::<K>().to_string(),
+            value: type_name::<V>().to_string(),
+        }
+    }
+}
+
+impl<K, V> Upgrade for LookupTable<K, V>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Value = V;
+
+    fn handle_old_event<Old, F>(&mut self, bytes: &[u8], upgrade: F) -> DbResult<()>
+    where
+        Old: DeserializeOwned,
+        F: FnMut(Old) -> V,
+    {
+        self.apply(bincode::deserialize::<LogEntry<K, Old>>(bytes)?.map(upgrade))
+    }
+}
+
+impl<K, V> TableView for LookupTable<K, V>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn serialized_size(&self) -> DbResult<u64> {
+        reflect::compact_size(self)
+    }
+
+    #[cfg(feature = "json")]
+    fn dump(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self.inner.iter().collect::<Vec<_>>())
     }
 }
 
 impl<K, V> LookupTable<K, V>
 where
-    K: Hash + Eq + Serialize,
-    V: Serialize,
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone,
 {
-    pub fn insert(&mut self, key: K, value: V) -> DbResult<Option<V>> {
-        let log_entry = LogEntry::Insert(&key, &value);
-        let data = bincode::serialize(&log_entry)?;
+    fn apply(&mut self, entry: LogEntry<K, V>) -> DbResult<()> {
+        let inner = Arc::make_mut(&mut self.inner);
+        match entry {
+            LogEntry::Insert(k, v) => {
+                inner.insert(k, v);
+            }
+            LogEntry::Remove(k) => {
+                inner.remove(&k);
+            }
+            LogEntry::Clear => {
+                inner.clear();
+            }
+        };
 
-        let ret = self.inner.insert(key, value);
+        Ok(())
+    }
 
+    pub fn insert(&mut self, key: K, value: V) -> DbResult<Option<V>> {
+        let data = bincode::serialize(&LogEntry::Insert(&key, &value))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(ret)
+
+        Ok(inner.insert(key, value))
     }
 
     pub fn remove(&mut self, key: &K) -> DbResult<Option<V>> {
-        let log_entry = LogEntry::Remove::<&K, &V>(key);
-        let data = bincode::serialize(&log_entry)?;
-        let ret = self.inner.remove(key);
+        if !self.inner.contains_key(key) {
+            return Ok(None);
+        }
+
+        let data = bincode::serialize(&LogEntry::Remove::<&K, &V>(key))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(ret)
+
+        Ok(inner.remove(key))
     }
 
     pub fn get(&self) -> &HashMap<K, V> {
@@ -92,11 +166,15 @@ where
     }
 
     pub fn clear(&mut self) -> DbResult<()> {
-        self.inner.clear();
-        let log_entry = LogEntry::<K, V>::Clear;
-        let data = bincode::serialize(&log_entry)?;
+        if self.inner.is_empty() {
+            return Ok(());
+        }
+
+        let data = bincode::serialize(&LogEntry::<K, V>::Clear)?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
 
+        inner.clear();
         Ok(())
     }
 }
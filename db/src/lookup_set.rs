@@ -1,9 +1,14 @@
-use crate::table::Table;
+use crate::descriptor::TableKind;
+use crate::reflect::{self, TableView};
+use crate::table::{Table, Upgrade};
 use crate::{DbResult, Logger, TableId};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::any::type_name;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::io::Write;
+use std::sync::Arc;
 
 /// A special case of [crate::lookup::LookupTable] where the value of the [HashMap] is a `HashSet<V>`.
 #[derive(Debug)]
@@ -14,7 +19,7 @@ where
     V: Serialize + Eq,
 {
     table_id: TableId,
-    inner: HashMap<K, HashSet<V>>,
+    inner: Arc<HashMap<K, HashSet<V>>>,
     pub logger: Logger,
 }
 
@@ -27,102 +32,173 @@ pub enum LogEntry<K, V> {
     Clear,
 }
 
+impl<K, V> LogEntry<K, V> {
+    fn map<W, F: FnMut(V) -> W>(self, mut f: F) -> LogEntry<K, W> {
+        match self {
+            LogEntry::Insert(k, v) => LogEntry::Insert(k, f(v)),
+            LogEntry::Remove(k, v) => LogEntry::Remove(k, f(v)),
+            LogEntry::CreateKey(k) => LogEntry::CreateKey(k),
+            LogEntry::ClearKey(k) => LogEntry::ClearKey(k),
+            LogEntry::Clear => LogEntry::Clear,
+        }
+    }
+}
+
 impl<K, V> Table for LookupSet<K, V>
 where
-    K: Hash + Eq + Serialize + DeserializeOwned,
-    V: Serialize + DeserializeOwned + Eq + Hash,
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Eq + Hash + Clone,
 {
     fn init(table_id: TableId, logger: Logger) -> Self {
-        Self { table_id, inner: HashMap::default(), logger }
+        Self { table_id, inner: Default::default(), logger }
     }
 
     fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()> {
-        match bincode::deserialize::<LogEntry<K, V>>(bytes)? {
-            LogEntry::Insert(k, v) => {
-                self.insert_inner(k, v);
-            }
-            LogEntry::Remove(k, v) => {
-                if let Some(x) = self.inner.get_mut(&k) {
-                    x.remove(&v);
-                }
-            }
-            LogEntry::CreateKey(k) => {
-                self.inner.insert(k, HashSet::new());
-            }
-            LogEntry::ClearKey(k) => {
-                self.inner.remove(&k);
-            }
-            LogEntry::Clear => {
-                self.inner.clear();
-            }
-        };
-
-        Ok(())
+        self.apply(bincode::deserialize(bytes)?)
     }
 
     fn compact_repr(&self) -> DbResult<Vec<u8>> {
         let mut repr = vec![];
-        for (k, values) in &self.inner {
+        self.write_compact_repr(&mut repr)?;
+        Ok(repr)
+    }
+
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        let mut data = vec![];
+        for (k, values) in self.inner.iter() {
             if values.is_empty() {
-                let data = bincode::serialize(&LogEntry::<&K, &V>::CreateKey(k))?;
-                let mut data = Logger::log_entry(self.table_id, data);
-                repr.append(&mut data);
+                data.clear();
+                bincode::serialize_into(&mut data, &LogEntry::<&K, &V>::CreateKey(k))?;
+                Logger::write_entry(out, self.table_id, &data)?;
                 continue;
             }
             for v in values {
-                let data = bincode::serialize(&LogEntry::Insert(k, v))?;
-                let mut data = Logger::log_entry(self.table_id, data);
-                repr.append(&mut data);
+                data.clear();
+                bincode::serialize_into(&mut data, &LogEntry::Insert(k, v))?;
+                Logger::write_entry(out, self.table_id, &data)?;
             }
         }
 
-        Ok(repr)
+        Ok(())
+    }
+
+    fn snapshot(&self, table_id: TableId, logger: Logger) -> DbResult<Self> {
+        Ok(Self { table_id, inner: self.inner.clone(), logger })
+    }
+
+    fn kind() -> TableKind {
+        TableKind::LookupSet {
+            key: type_name::<K>().to_string(),
+            value: type_name::<V>().to_string(),
+        }
+    }
+}
+
+impl<K, V> Upgrade for LookupSet<K, V>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Eq + Hash + Clone,
+{
+    type Value = V;
+
+    fn handle_old_event<Old, F>(&mut self, bytes: &[u8], upgrade: F) -> DbResult<()>
+    where
+        Old: DeserializeOwned,
+        F: FnMut(Old) -> V,
+    {
+        self.apply(bincode::deserialize::<LogEntry<K, Old>>(bytes)?.map(upgrade))
+    }
+}
+
+impl<K, V> TableView for LookupSet<K, V>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Eq + Hash + Clone,
+{
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn serialized_size(&self) -> DbResult<u64> {
+        reflect::compact_size(self)
+    }
+
+    #[cfg(feature = "json")]
+    fn dump(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self.inner.iter().collect::<Vec<_>>())
     }
 }
 
 impl<K, V> LookupSet<K, V>
 where
-    K: Hash + Eq + Serialize + DeserializeOwned,
-    V: Serialize + DeserializeOwned + Eq + Hash,
+    K: Hash + Eq + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Eq + Hash + Clone,
 {
-    pub(crate) fn insert_inner(&mut self, k: K, v: V) -> bool {
-        if let Some(set) = self.inner.get_mut(&k) {
+    fn apply(&mut self, entry: LogEntry<K, V>) -> DbResult<()> {
+        match entry {
+            LogEntry::Insert(k, v) => {
+                Self::insert_inner(Arc::make_mut(&mut self.inner), k, v);
+            }
+            LogEntry::Remove(k, v) => {
+                if let Some(x) = Arc::make_mut(&mut self.inner).get_mut(&k) {
+                    x.remove(&v);
+                }
+            }
+            LogEntry::CreateKey(k) => {
+                Arc::make_mut(&mut self.inner).insert(k, HashSet::new());
+            }
+            LogEntry::ClearKey(k) => {
+                Arc::make_mut(&mut self.inner).remove(&k);
+            }
+            LogEntry::Clear => {
+                Arc::make_mut(&mut self.inner).clear();
+            }
+        };
+
+        Ok(())
+    }
+
+    fn insert_inner(inner: &mut HashMap<K, HashSet<V>>, k: K, v: V) -> bool {
+        if let Some(set) = inner.get_mut(&k) {
             set.insert(v)
         } else {
             let mut set = HashSet::new();
             set.insert(v);
-            self.inner.insert(k, set);
+            inner.insert(k, set);
             false
         }
     }
 
     pub fn insert(&mut self, key: K, value: V) -> DbResult<bool> {
-        let log_entry = LogEntry::Insert(&key, &value);
-        let data = bincode::serialize(&log_entry)?;
-        let ret = self.insert_inner(key, value);
+        let data = bincode::serialize(&LogEntry::Insert(&key, &value))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(ret)
+
+        Ok(Self::insert_inner(inner, key, value))
     }
 
     pub fn create_key(&mut self, key: K) -> DbResult<Option<HashSet<V>>> {
-        let log_entry = LogEntry::<&K, &V>::CreateKey(&key);
-        let data = bincode::serialize(&log_entry)?;
-
-        let ret = self.inner.insert(key, HashSet::new());
-
+        let data = bincode::serialize(&LogEntry::<&K, &V>::CreateKey(&key))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(ret)
+
+        Ok(inner.insert(key, HashSet::new()))
     }
 
     pub fn remove(&mut self, key: &K, value: &V) -> DbResult<bool> {
-        if let Some(set) = self.inner.get_mut(key) {
-            let log_entry = LogEntry::Remove::<&K, &V>(key, value);
-            let data = bincode::serialize(&log_entry)?;
-            self.logger.write(self.table_id, data)?;
-            Ok(set.remove(value))
-        } else {
-            Ok(false)
+        match self.inner.get(key) {
+            Some(set) if set.contains(value) => {}
+            _ => return Ok(false),
+        }
+
+        let data = bincode::serialize(&LogEntry::Remove::<&K, &V>(key, value))?;
+        let inner = Arc::make_mut(&mut self.inner);
+        self.logger.write(self.table_id, data)?;
+
+        if let Some(set) = inner.get_mut(key) {
+            set.remove(value);
         }
+        Ok(true)
     }
 
     pub fn get(&self) -> &HashMap<K, HashSet<V>> {
@@ -130,20 +206,27 @@ where
     }
 
     pub fn clear(&mut self) -> DbResult<()> {
-        self.inner.clear();
-        let log_entry = LogEntry::<K, V>::Clear;
-        let data = bincode::serialize(&log_entry)?;
+        if self.inner.is_empty() {
+            return Ok(());
+        }
+
+        let data = bincode::serialize(&LogEntry::<K, V>::Clear)?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
 
+        inner.clear();
         Ok(())
     }
 
     pub fn clear_key(&mut self, key: &K) -> DbResult<Option<HashSet<V>>> {
-        let log_entry = LogEntry::<&K, &V>::ClearKey(key);
-        let data = bincode::serialize(&log_entry)?;
-        let ret = self.inner.remove(key);
+        if !self.inner.contains_key(key) {
+            return Ok(None);
+        }
+
+        let data = bincode::serialize(&LogEntry::<&K, &V>::ClearKey(key))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
 
-        Ok(ret)
+        Ok(inner.remove(key))
     }
 }
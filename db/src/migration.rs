@@ -0,0 +1,76 @@
+//! Moving a db's data to a new schema when the shape of its tables changes, see [migrate].
+
+use crate::logger::Logger;
+use crate::{Config, Db, DbError, DbResult};
+use std::fs;
+
+/// Move the data in the log of the `Old` schema into a new log for the `New` schema, with `with`
+/// building the new tables from the old ones. `#[derive(Schema)]` calls this from `init` for
+/// schemas marked with `#[schema(migrate_from = Old, with = with)]`.
+///
+/// Nothing happens unless there's an `Old` log and no `New` log. Otherwise `Old` is opened
+/// (running its own migration first, if it has one), `with` fills an in memory `New`, and a
+/// compacted log of `New` is written next to the old log and renamed into place once it's complete.
+/// The new log records in its metadata that it was migrated from `Old`, so if the process stops
+/// before the old log is moved to [Config::archive_location], the next `init` finishes the
+/// migration by archiving it rather than migrating again.
+///
+/// A [Config::read_only] config never writes or archives a log, and one without
+/// [Config::create_db] never creates one, so with either a pending migration fails with
+/// [DbError::MigrationPending] and the folder is left as it is.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err, fields(from = Old::schema_name(), to = New::schema_name())))]
+pub fn migrate<Old, New>(config: &Config, with: fn(&Old, &mut New) -> DbResult<()>) -> DbResult<()>
+where
+    Old: Db,
+    New: Db,
+{
+    if config.no_io {
+        return Ok(());
+    }
+
+    let old_config = config.for_schema(Old::schema_name());
+    let old_path = old_config.db_location_v2()?;
+
+    if let Some(meta) = Logger::read_metadata(config)? {
+        if meta.migrated_from() == Some(Old::schema_name())
+            && old_path.exists()
+            && !config.read_only
+        {
+            fs::rename(&old_path, old_config.archive_location()?)?;
+            #[cfg(feature = "tracing")]
+            tracing::info!("archived log left behind by an interrupted migration");
+        }
+        return Ok(());
+    }
+
+    // Logger::init moves logs from the v1 location, they're never migrated
+    if config.db_location_v1()?.exists() {
+        return Ok(());
+    }
+
+    if !Old::log_exists(config)? {
+        return Ok(());
+    }
+
+    if config.read_only || !config.create_db {
+        return Err(DbError::MigrationPending { from: Old::schema_name(), to: New::schema_name() });
+    }
+
+    let old = Old::init(old_config.clone())?;
+    let mut new = New::init(Config::no_io())?;
+    with(&old, &mut new)?;
+    Logger::create_migrated(config, Old::schema_name(), |out| new.write_compact_repr(out))?;
+
+    drop(old);
+    fs::rename(old_path, old_config.archive_location()?)?;
+    #[cfg(feature = "tracing")]
+    tracing::info!("migrated schema");
+
+    Ok(())
+}
+
+/// Whether `D` has a log of its own in `config`'s folder, see [Db::log_exists].
+pub fn own_log_exists<D: Db>(config: &Config) -> DbResult<bool> {
+    let config = config.for_schema(D::schema_name());
+    Ok(config.db_location_v2()?.exists() || config.db_location_v1()?.exists())
+}
@@ -0,0 +1,88 @@
+//! Looking at the tables of a schema without knowing its types, for tools like admin screens and
+//! exports. `#[derive(Schema)]` implements [Reflect] for schemas marked with `#[schema(reflect)]`.
+
+use crate::table::Table;
+use crate::{DbResult, TableId};
+use std::io::{self, Write};
+
+/// A field of a schema, see [Reflect::TABLES].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableInfo {
+    /// the name of the field
+    pub name: &'static str,
+    /// the id of the table, or the first id of a nested schema's tables. Ids are those of the
+    /// schema opened on its own, the ids of a nested schema's tables start at the id of its field.
+    pub id: TableId,
+    /// the type of the field, as it's written in the schema
+    pub type_name: &'static str,
+    /// the fields of a nested schema, empty for a table
+    pub nested: &'static [TableInfo],
+}
+
+/// A schema whose tables can be listed and visited, see [crate::reflect].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't support reflection",
+    label = "the nested schemas of a schema with `#[schema(reflect)]` need it too",
+    note = "add `#[schema(reflect)]` to `{Self}`"
+)]
+pub trait Reflect {
+    /// The fields of the schema in the order of their ids, skipped fields aside.
+    const TABLES: &'static [TableInfo];
+
+    /// Call `visit` with the id, [TableInfo] and contents of every table, including those of
+    /// nested schemas.
+    fn visit_tables(&self, visit: &mut dyn FnMut(TableId, &'static TableInfo, &dyn TableView)) {
+        self.visit_tables_from(1, visit)
+    }
+
+    /// [Reflect::visit_tables] for a schema whose ids start at `first_id`.
+    #[doc(hidden)]
+    fn visit_tables_from(
+        &self, first_id: TableId,
+        visit: &mut dyn FnMut(TableId, &'static TableInfo, &dyn TableView),
+    );
+}
+
+/// What a tool can see of a table without knowing its types, implemented by the built-in tables.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be reflected",
+    label = "the tables of a schema with `#[schema(reflect)]` must implement `TableView`",
+    note = "implement `db_rs::reflect::TableView` for `{Self}`"
+)]
+pub trait TableView {
+    /// the number of values in the table, or keys for lookup tables
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The size of the table in a freshly compacted log, headers included.
+    fn serialized_size(&self) -> DbResult<u64>;
+
+    /// The contents of the table as json. Lookup tables are dumped as a list of `[key, value]`
+    /// pairs, as their keys needn't be strings.
+    #[cfg(feature = "json")]
+    fn dump(&self) -> serde_json::Result<serde_json::Value>;
+}
+
+/// The size of `table`'s [Table::write_compact_repr], without holding on to it.
+pub(crate) fn compact_size<T: Table>(table: &T) -> DbResult<u64> {
+    let mut size = ByteCounter(0);
+    table.write_compact_repr(&mut size)?;
+    Ok(size.0)
+}
+
+/// A writer that only counts the bytes written to it.
+pub(crate) struct ByteCounter(pub(crate) u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
+use crate::descriptor::TableKind;
 use crate::errors::DbResult;
 use crate::logger::Logger;
-use crate::table::Table;
+use crate::reflect::{self, TableView};
+use crate::table::{Table, Upgrade};
 use crate::TableId;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::any::type_name;
+use std::io::Write;
+use std::sync::Arc;
 
 /// A table which stores 0 or 1 values -- backed by [Option]
 #[derive(Debug)]
@@ -13,59 +18,108 @@ where
     T: Serialize + DeserializeOwned,
 {
     table_id: TableId,
-    inner: Option<T>,
+    inner: Arc<Option<T>>,
     pub logger: Logger,
 }
 
 impl<T> Table for Single<T>
 where
-    T: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
 {
     fn init(table_id: TableId, logger: Logger) -> Self {
-        Self { table_id, inner: None, logger }
+        Self { table_id, inner: Default::default(), logger }
     }
 
     fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()> {
-        self.inner = bincode::deserialize(bytes)?;
+        self.inner = Arc::new(bincode::deserialize(bytes)?);
 
         Ok(())
     }
 
     fn compact_repr(&self) -> DbResult<Vec<u8>> {
-        if let Some(v) = &self.inner {
+        let mut repr = vec![];
+        self.write_compact_repr(&mut repr)?;
+        Ok(repr)
+    }
+
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        if let Some(v) = self.inner.as_ref() {
             let data = bincode::serialize(&Some(v))?;
-            let data = Logger::log_entry(self.table_id, data);
-            Ok(data)
-        } else {
-            Ok(vec![])
+            Logger::write_entry(out, self.table_id, &data)?;
         }
+
+        Ok(())
+    }
+
+    fn snapshot(&self, table_id: TableId, logger: Logger) -> DbResult<Self> {
+        Ok(Self { table_id, inner: self.inner.clone(), logger })
+    }
+
+    fn kind() -> TableKind {
+        TableKind::Single { value: type_name::<T>().to_string() }
     }
 }
 
-impl<T> Single<T>
+impl<T> Upgrade for Single<T>
 where
-    T: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned + Clone,
 {
-    pub fn insert(&mut self, value: T) -> DbResult<Option<T>> {
-        let log_entry = Some(&value);
-        let data = bincode::serialize(&log_entry)?;
+    type Value = T;
+
+    fn handle_old_event<Old, F>(&mut self, bytes: &[u8], upgrade: F) -> DbResult<()>
+    where
+        Old: DeserializeOwned,
+        F: FnMut(Old) -> T,
+    {
+        self.inner = Arc::new(bincode::deserialize::<Option<Old>>(bytes)?.map(upgrade));
+
+        Ok(())
+    }
+}
+
+impl<T> TableView for Single<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn len(&self) -> usize {
+        self.inner.is_some() as usize
+    }
+
+    fn serialized_size(&self) -> DbResult<u64> {
+        reflect::compact_size(self)
+    }
 
-        let ret = self.inner.replace(value);
+    #[cfg(feature = "json")]
+    fn dump(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(&*self.inner)
+    }
+}
 
+impl<T> Single<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    pub fn insert(&mut self, value: T) -> DbResult<Option<T>> {
+        let data = bincode::serialize(&Some(&value))?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
 
-        Ok(ret)
+        Ok(inner.replace(value))
     }
 
     pub fn get(&self) -> Option<&T> {
-        self.inner.as_ref()
+        self.inner.as_ref().as_ref()
     }
 
     pub fn clear(&mut self) -> DbResult<Option<T>> {
-        let log_entry = Option::<T>::None;
-        let data = bincode::serialize(&log_entry)?;
-        let ret = self.inner.take();
+        if self.inner.is_none() {
+            return Ok(None);
+        }
+
+        let data = bincode::serialize(&Option::<T>::None)?;
+        let inner = Arc::make_mut(&mut self.inner);
         self.logger.write(self.table_id, data)?;
-        Ok(ret)
+
+        Ok(inner.take())
     }
 }
@@ -0,0 +1,46 @@
+use crate::logger::LogPosition;
+use crate::{Config, Db, DbResult, Logger};
+use std::ops::Deref;
+
+/// A read-only, point-in-time view of every table in a [Db], created with [Db::snapshot].
+///
+/// Tables share their data with the snapshot until they're written to, at which point the written
+/// table makes its own copy. A snapshot reflects the db as of the last committed transaction and
+/// never changes, so it can be read from other threads while writes continue.
+#[derive(Debug)]
+pub struct Snapshot<D> {
+    db: D,
+    source: Logger,
+    position: LogPosition,
+}
+
+impl<D> Snapshot<D>
+where
+    D: Db,
+{
+    #[doc(hidden)]
+    pub fn new<F>(source: &Logger, tables: F) -> DbResult<Self>
+    where
+        F: FnOnce(Logger) -> DbResult<D>,
+    {
+        let position = source.position()?;
+        let db = tables(Logger::init(Config::no_io())?)?;
+        Ok(Self { db, source: source.clone(), position })
+    }
+
+    /// Compact the log of the db this snapshot was taken from, using the contents of this
+    /// snapshot. Writes that happened after the snapshot was taken are carried over, so writers
+    /// are only blocked while those are copied.
+    pub fn compact_log(&self) -> DbResult<()> {
+        self.source
+            .compact_log_since(|out| self.db.write_compact_repr(out), self.position.clone())
+    }
+}
+
+impl<D> Deref for Snapshot<D> {
+    type Target = D;
+
+    fn deref(&self) -> &Self::Target {
+        &self.db
+    }
+}
@@ -0,0 +1,50 @@
+use crate::TableId;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Numbers describing the log, tracked as writes happen so they're cheap to read. "The last
+/// compaction" refers to when the log was opened if it hasn't been compacted since.
+#[derive(Clone, Debug)]
+pub struct LogStats {
+    /// size of the log in bytes, including writes that haven't been flushed yet
+    pub log_size: u64,
+
+    /// how much of the log is live data: its size right after the last compaction, or when it was
+    /// opened, the size compacting it would leave it at
+    pub compacted_size: u64,
+
+    /// writes made to any table since the last compaction
+    pub writes_since_compaction: u64,
+
+    /// time elapsed since the last compaction
+    pub since_compaction: Duration,
+}
+
+/// Everything db-rs knows about a db's log, see [crate::Db::stats].
+#[derive(Clone, Debug)]
+pub struct DbStats {
+    pub log: LogStats,
+
+    /// the compaction count recorded in the log file, which survives restarts but wraps at 255
+    pub generation: u8,
+
+    /// numbers for each table that has entries in the log or was written to, by table id
+    pub tables: BTreeMap<TableId, TableStats>,
+
+    /// the io error that stopped the log from accepting writes, see [crate::Db::recover]
+    pub failure: Option<String>,
+}
+
+/// Numbers describing a single table's share of the log, see [DbStats].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// entries in the log that belong to this table, including ones made redundant by later
+    /// writes
+    pub entries: u64,
+
+    /// serialized size of those entries, excluding their headers
+    pub bytes: u64,
+
+    /// writes made to this table since the last compaction
+    pub writes_since_compaction: u64,
+}
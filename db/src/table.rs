@@ -1,9 +1,64 @@
+use crate::descriptor::TableKind;
 use crate::errors::DbResult;
 use crate::logger::Logger;
 use crate::TableId;
+use serde::de::DeserializeOwned;
+use std::any::type_name;
+use std::io::Write;
 
+/// A table in a [crate::Db]. Built-in tables are write-ahead: every operation serializes and logs
+/// its entry before it changes anything in memory, so a failed write leaves the table as it was.
+/// Operations that wouldn't change anything aren't logged.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a db-rs table",
+    label = "the fields of a schema must be tables",
+    note = "use one of db-rs's tables, like `Single<{Self}>` or `LookupTable<K, {Self}>`, or implement `Table`"
+)]
 pub trait Table {
     fn init(table_id: TableId, logger: Logger) -> Self;
     fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()>;
     fn compact_repr(&self) -> DbResult<Vec<u8>>;
+
+    /// Write [Table::compact_repr] into `out`. Built-in tables override this to write one entry at
+    /// a time rather than building their whole compact representation in memory.
+    fn write_compact_repr(&self, out: &mut dyn Write) -> DbResult<()> {
+        out.write_all(&self.compact_repr()?)?;
+        Ok(())
+    }
+
+    /// A read-only copy of this table that writes to `logger`. Built-in tables share their data
+    /// with `self` until one of them is written to. The default implementation replays
+    /// [Table::compact_repr] into a fresh table.
+    fn snapshot(&self, table_id: TableId, logger: Logger) -> DbResult<Self>
+    where
+        Self: Sized,
+    {
+        let repr = self.compact_repr()?;
+        let mut table = Self::init(table_id, logger.clone());
+        for entry in logger.get_entries(&repr)? {
+            table.handle_event(entry.bytes)?;
+        }
+        Ok(table)
+    }
+
+    /// What kind of table this is and the types it holds, for the [crate::descriptor] of a schema.
+    fn kind() -> TableKind
+    where
+        Self: Sized,
+    {
+        TableKind::Custom { type_name: type_name::<Self>().to_string() }
+    }
+}
+
+/// A table that can replay entries logged while its values were of an older type, used by
+/// `#[table(upgrade = [Old -> New])]` when an entry doesn't deserialize as the current type.
+/// Compacting the log rewrites these entries with the current type.
+pub trait Upgrade: Table {
+    type Value;
+
+    /// Apply an entry whose values are `Old`s, converting each of them with `upgrade`.
+    fn handle_old_event<Old, F>(&mut self, bytes: &[u8], upgrade: F) -> DbResult<()>
+    where
+        Old: DeserializeOwned,
+        F: FnMut(Old) -> Self::Value;
 }
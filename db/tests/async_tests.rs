@@ -0,0 +1,162 @@
+// cargo test -F tokio
+#[cfg(feature = "tokio")]
+mod async_feature {
+    use db_rs::compacter::AsyncBackgroundCompacter;
+    use db_rs::{AsyncDb, CancelSig, Config, Db, List, LookupTable};
+    use db_rs_derive::Schema;
+    use std::fs;
+    use std::time::Duration;
+
+    #[derive(Schema)]
+    struct AsyncTest {
+        table1: LookupTable<u32, String>,
+        table2: List<u32>,
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_transactions() {
+        let dir = "/tmp/async1/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+
+        let tasks: Vec<_> = (0..100)
+            .map(|i| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    db.transaction(move |db| {
+                        db.table1.insert(i, format!("{i}"))?;
+                        db.table2.push(i)
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(db.read().unwrap().table1.get().len(), 100);
+        assert_eq!(db.read().unwrap().table2.get().len(), 100);
+
+        db.close().await.unwrap();
+        let db = AsyncTest::init(Config::in_folder(dir)).unwrap();
+        assert_eq!(db.table1.get().get(&42).unwrap(), "42");
+        assert_eq!(db.table2.get().len(), 100);
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn close_stops_the_writer() {
+        let dir = "/tmp/async6/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+        let other = db.clone();
+
+        db.transaction(|db| db.table2.push(1)).await.unwrap();
+        db.close().await.unwrap();
+        let result = other.transaction(|db| db.table2.push(2)).await;
+        assert!(matches!(result, Err(db_rs::DbError::WriterGone)));
+
+        drop(other);
+        let db = AsyncTest::init(Config::in_folder(dir)).unwrap();
+        assert_eq!(db.table2.get(), [1]);
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transaction_errors_are_returned() {
+        let dir = "/tmp/async2/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+
+        let prev = db
+            .transaction(|db| db.table1.insert(1, "a".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(prev, None);
+
+        let result: db_rs::DbResult<()> = db
+            .transaction(|_| Err(db_rs::DbError::Unexpected("test")))
+            .await;
+        assert!(result.is_err());
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn writer_survives_failures() {
+        let dir = "/tmp/async5/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+
+        let result: db_rs::DbResult<()> = db.transaction(|_| panic!("in a transaction")).await;
+        assert!(matches!(result, Err(db_rs::DbError::TxPanicked)));
+        db.transaction(|db| db.table2.push(1)).await.unwrap();
+
+        // a thread that panics while holding the lock poisons it for every later group
+        let concurrent = db.concurrent().clone();
+        std::thread::spawn(move || {
+            let _db = concurrent.write().unwrap();
+            panic!("holding the lock");
+        })
+        .join()
+        .unwrap_err();
+        let result = db.transaction(|db| db.table2.push(2)).await;
+        assert!(matches!(result, Err(db_rs::DbError::MutexPoisoned)));
+        let result = db.transaction(|db| db.table2.push(3)).await;
+        assert!(matches!(result, Err(db_rs::DbError::MutexPoisoned)));
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_compacter() {
+        let dir = "/tmp/async3/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+        for i in 0..10 {
+            db.transaction(move |db| db.table2.push(i)).await.unwrap();
+        }
+
+        let cancel = CancelSig::default();
+        let handle = db.begin_compacter(Duration::from_millis(100), cancel.clone());
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        cancel.cancel();
+        // the second tick finds nothing to compact
+        assert_eq!(handle.await.unwrap().unwrap(), 1);
+        assert_eq!(db.read().unwrap().table2.get().len(), 10);
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[tokio::test]
+    async fn async_compacter_cancel_is_immediate() {
+        let dir = "/tmp/async4/";
+        drop(fs::remove_dir_all(dir));
+        let db = AsyncDb::<AsyncTest>::init(Config::in_folder(dir))
+            .await
+            .unwrap();
+
+        let cancel = CancelSig::default();
+        let handle = db.begin_compacter(Duration::from_secs(3600), cancel.clone());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+        let stopped = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        assert_eq!(stopped.unwrap().unwrap().unwrap(), 0);
+
+        drop(fs::remove_dir_all(dir));
+    }
+}
@@ -0,0 +1,90 @@
+use db_rs::compacter::BackgroundCompacter;
+use db_rs::{CancelSig, CompacterConfig, ConcurrentDb, Config, Db, DbError, LookupTable};
+use db_rs_derive::Schema;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Schema)]
+struct CompacterTest {
+    table: LookupTable<u8, String>,
+}
+
+#[test]
+fn cancel_is_immediate() {
+    let dir = "/tmp/compacter1/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<CompacterTest>::init(Config::in_folder(dir)).unwrap();
+
+    let cancel = CancelSig::default();
+    let handle = db.begin_compacter(Duration::from_secs(3600), cancel.clone());
+    thread::sleep(Duration::from_millis(20));
+
+    let start = Instant::now();
+    cancel.cancel();
+    assert_eq!(handle.join().unwrap().unwrap(), 0);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compact_now() {
+    let dir = "/tmp/compacter2/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<CompacterTest>::init(Config::in_folder(dir)).unwrap();
+    db.transaction(|db| {
+        for i in 0..5 {
+            db.table.insert(1, format!("{i}"))?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let handle = db.spawn_compacter(CompacterConfig::every(Duration::from_secs(3600)));
+    handle.compact_now();
+    handle
+        .results()
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        db.read()
+            .unwrap()
+            .log_stats()
+            .unwrap()
+            .writes_since_compaction,
+        0
+    );
+
+    handle.cancel();
+    assert_eq!(handle.join().unwrap(), 1);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn poisoned_mutex_is_reported() {
+    let dir = "/tmp/compacter3/";
+    drop(fs::remove_dir_all(dir));
+    let db = Arc::new(Mutex::new(CompacterTest::init(Config::in_folder(dir)).unwrap()));
+
+    let poisoner = db.clone();
+    thread::spawn(move || {
+        let _guard = poisoner.lock().unwrap();
+        panic!("poison the db");
+    })
+    .join()
+    .unwrap_err();
+
+    let handle = db.spawn_compacter(CompacterConfig::every(Duration::from_millis(10)));
+    let result = handle
+        .results()
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert!(matches!(result, Err(DbError::MutexPoisoned)));
+    assert!(matches!(handle.join(), Err(DbError::MutexPoisoned)));
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,168 @@
+use db_rs::compacter::BackgroundCompacter;
+use db_rs::{CancelSig, CompactionPolicy, ConcurrentDb, Config, Db, LookupTable};
+use db_rs_derive::Schema;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Schema)]
+struct PolicyTest {
+    table: LookupTable<u8, String>,
+}
+
+#[test]
+fn compact_after_writes() {
+    let dir = "/tmp/policy1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    let policy = CompactionPolicy { max_writes: Some(10), ..Default::default() };
+
+    for i in 0..9 {
+        db.table.insert(1, format!("{i}")).unwrap();
+    }
+    assert!(!db.compact_if(&policy).unwrap());
+    db.table.insert(1, "last".to_string()).unwrap();
+    assert_eq!(db.log_stats().unwrap().writes_since_compaction, 10);
+    assert!(db.compact_if(&policy).unwrap());
+
+    let stats = db.log_stats().unwrap();
+    assert_eq!(stats.writes_since_compaction, 0);
+    assert_eq!(stats.log_size, stats.compacted_size);
+    assert!(!db.compact_if(&policy).unwrap());
+
+    drop(db);
+    let db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.table.get().get(&1).unwrap(), "last");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compact_on_size_and_growth() {
+    let dir = "/tmp/policy2/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+
+    let by_size = CompactionPolicy { max_log_size: Some(1000), ..Default::default() };
+    let by_growth = CompactionPolicy { max_growth_ratio: Some(4.0), ..Default::default() };
+
+    db.table.insert(1, "a".repeat(100)).unwrap();
+    assert!(!db.compact_if(&by_size).unwrap());
+    for _ in 0..10 {
+        db.table.insert(1, "a".repeat(100)).unwrap();
+    }
+    assert!(db.log_stats().unwrap().log_size >= 1000);
+    assert!(db.compact_if(&by_size).unwrap());
+
+    db.table.insert(1, "a".repeat(100)).unwrap();
+    assert!(!db.compact_if(&by_growth).unwrap());
+    for _ in 0..3 {
+        db.table.insert(1, "a".repeat(100)).unwrap();
+    }
+    assert!(db.compact_if(&by_growth).unwrap());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn min_interval() {
+    let dir = "/tmp/policy3/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    let policy = CompactionPolicy {
+        max_writes: Some(1),
+        min_interval: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    db.table.insert(1, "a".to_string()).unwrap();
+    assert!(!db.compact_if(&policy).unwrap());
+    thread::sleep(Duration::from_millis(100));
+    assert!(db.compact_if(&policy).unwrap());
+    db.table.insert(1, "a".to_string()).unwrap();
+    assert!(!db.compact_if(&policy).unwrap());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn background_policy_compacter() {
+    let dir = "/tmp/policy4/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<PolicyTest>::init(Config::in_folder(dir)).unwrap();
+    let policy = CompactionPolicy { max_writes: Some(5), ..Default::default() };
+
+    let cancel = CancelSig::default();
+    let handle = db.begin_policy_compacter(Duration::from_millis(20), policy, cancel.clone());
+    thread::sleep(Duration::from_millis(100));
+    db.transaction(|db| {
+        for i in 0..5 {
+            db.table.insert(i, "a".to_string())?;
+        }
+        Ok(())
+    })
+    .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    cancel.cancel();
+
+    assert_eq!(handle.join().unwrap().unwrap(), 1);
+    assert_eq!(
+        db.read()
+            .unwrap()
+            .log_stats()
+            .unwrap()
+            .writes_since_compaction,
+        0
+    );
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn unchanged_log_is_not_compacted() {
+    let dir = "/tmp/policy5/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    let always = CompactionPolicy::default();
+
+    db.table.insert(1, "a".to_string()).unwrap();
+    assert!(db.compact_if(&always).unwrap());
+    assert!(!db.compact_if(&always).unwrap());
+    drop(db);
+
+    // the background compacter doesn't snapshot a log that has nothing to compact
+    let db = ConcurrentDb::<PolicyTest>::init(Config::in_folder(dir)).unwrap();
+    let cancel = CancelSig::default();
+    let handle = db.begin_compacter(Duration::from_millis(10), cancel.clone());
+    thread::sleep(Duration::from_millis(100));
+    db.transaction(|db| db.table.insert(1, "b".to_string()).map(drop))
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    cancel.cancel();
+    assert_eq!(handle.join().unwrap().unwrap(), 1);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn growth_is_measured_against_live_data() {
+    let dir = "/tmp/policy6/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    for _ in 0..10 {
+        db.table.insert(1, "a".repeat(100)).unwrap();
+    }
+    drop(db);
+
+    // only the last insert is live, so the reopened log is already 10 times its live data
+    let mut db = PolicyTest::init(Config::in_folder(dir)).unwrap();
+    let stats = db.log_stats().unwrap();
+    assert!(stats.log_size >= stats.compacted_size * 8);
+    let by_growth = CompactionPolicy { max_growth_ratio: Some(4.0), ..Default::default() };
+    assert!(db.compact_if(&by_growth).unwrap());
+    let stats = db.log_stats().unwrap();
+    assert_eq!(stats.log_size, stats.compacted_size);
+    assert!(!db.compact_if(&CompactionPolicy::default()).unwrap());
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,71 @@
+use db_rs::{ConcurrentDb, Config, Db, List, Single};
+use db_rs_derive::Schema;
+use std::fs;
+use std::thread;
+
+#[derive(Schema)]
+struct Bank {
+    total: Single<u64>,
+    deposits: List<u64>,
+}
+
+#[test]
+fn readers_see_whole_transactions() {
+    let dir = "/tmp/concurrent1/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<Bank>::init(Config::in_folder(dir)).unwrap();
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for _ in 0..500 {
+                    let db = db.read().unwrap();
+                    let total = db.total.get().copied().unwrap_or_default();
+                    assert_eq!(total, db.deposits.get().iter().sum::<u64>());
+                }
+            })
+        })
+        .collect();
+
+    for i in 1..=100 {
+        db.transaction(|db| {
+            db.deposits.push(i)?;
+            let total = db.total.get().copied().unwrap_or_default();
+            db.total.insert(total + i)?;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    drop(db);
+    let db = Bank::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.total.get(), Some(&5050));
+    assert_eq!(db.deposits.get().len(), 100);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn transaction_returns_value() {
+    let dir = "/tmp/concurrent2/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<Bank>::init(Config::in_folder(dir)).unwrap();
+
+    let prev = db.transaction(|db| db.total.insert(5)).unwrap();
+    assert_eq!(prev, None);
+    let prev = db.transaction(|db| db.total.insert(6)).unwrap();
+    assert_eq!(prev, Some(5));
+
+    db.compact_log().unwrap();
+    drop(db);
+
+    let db = Bank::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.total.get(), Some(&6));
+
+    drop(fs::remove_dir_all(dir));
+}
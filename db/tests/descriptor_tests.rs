@@ -0,0 +1,130 @@
+use db_rs::descriptor::{SchemaDescriptor, TableDescriptor, TableKind};
+use db_rs::{Config, Db, List, Logger, LookupList, LookupTable, Single};
+use db_rs_derive::Schema;
+use std::fs;
+
+#[derive(Schema)]
+struct Files {
+    docs: LookupTable<u64, String>,
+    versions: LookupList<u64, u32>,
+}
+
+#[derive(Schema)]
+#[schema(name = "app", describe)]
+struct App {
+    owner: Single<String>,
+    #[schema(nested)]
+    files: Files,
+}
+
+#[derive(Schema)]
+#[schema(name = "app", describe)]
+struct AppWithLog {
+    owner: Single<String>,
+    #[schema(nested)]
+    files: Files,
+    log: List<u8>,
+}
+
+#[derive(Schema)]
+#[schema(name = "app")]
+struct Undescribed {
+    owner: Single<String>,
+}
+
+fn path(dir: &str) -> String {
+    format!("{dir}app.db")
+}
+
+#[test]
+fn tables_are_described() {
+    let descriptor = SchemaDescriptor::of::<App>();
+    assert_eq!(descriptor.name, "app");
+
+    let string = "alloc::string::String".to_string();
+    assert_eq!(
+        descriptor.tables,
+        [
+            TableDescriptor {
+                name: "owner".into(),
+                id: 1,
+                kind: TableKind::Single { value: string.clone() }
+            },
+            TableDescriptor {
+                name: "files.docs".into(),
+                id: 2,
+                kind: TableKind::LookupTable { key: "u64".into(), value: string },
+            },
+            TableDescriptor {
+                name: "files.versions".into(),
+                id: 3,
+                kind: TableKind::LookupList { key: "u64".into(), value: "u32".into() },
+            },
+        ]
+    );
+    assert_eq!(descriptor.table(3).unwrap().name, "files.versions");
+    assert!(descriptor.table(4).is_none());
+}
+
+#[test]
+fn descriptor_is_stored_in_the_log() {
+    let dir = "/tmp/descriptor1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = App::init(Config::in_folder(dir)).unwrap();
+    db.owner.insert("parth".into()).unwrap();
+    db.files.docs.insert(1, "readme".into()).unwrap();
+    assert_eq!(SchemaDescriptor::read(path(dir)).unwrap(), Some(SchemaDescriptor::of::<App>()));
+
+    db.compact_log().unwrap();
+    assert_eq!(SchemaDescriptor::read(path(dir)).unwrap(), Some(SchemaDescriptor::of::<App>()));
+    drop(db);
+
+    // a tool that doesn't know the schema's types can still tell the entries apart
+    let descriptor = SchemaDescriptor::read(path(dir)).unwrap().unwrap();
+    let mut config = Config::in_folder(dir);
+    config.file_stem = Some("app".into());
+    config.read_only = true;
+    config.fs_locks = false;
+    let log = Logger::init(config).unwrap();
+    let bytes = log.get_bytes().unwrap();
+    let tables: Vec<_> = log
+        .get_entries(&bytes)
+        .unwrap()
+        .iter()
+        .map(|entry| descriptor.table(entry.table_id).unwrap().name.clone())
+        .collect();
+    assert_eq!(tables, ["owner", "files.docs"]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn descriptor_follows_the_schema() {
+    let dir = "/tmp/descriptor2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = Undescribed::init(Config::in_folder(dir)).unwrap();
+    db.owner.insert("travis".into()).unwrap();
+    drop(db);
+    assert_eq!(SchemaDescriptor::read(path(dir)).unwrap(), None);
+    assert_eq!(fs::read(path(dir)).unwrap()[0], 2);
+
+    let db = App::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.owner.get().unwrap(), "travis");
+    assert_eq!(SchemaDescriptor::read(path(dir)).unwrap(), Some(SchemaDescriptor::of::<App>()));
+    drop(db);
+
+    let mut db = AppWithLog::init(Config::in_folder(dir)).unwrap();
+    db.log.push(1).unwrap();
+    let descriptor = SchemaDescriptor::read(path(dir)).unwrap().unwrap();
+    assert_eq!(descriptor, SchemaDescriptor::of::<AppWithLog>());
+    assert_eq!(descriptor.table(4).unwrap().name, "log");
+    drop(db);
+
+    let db = AppWithLog::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.owner.get().unwrap(), "travis");
+    assert_eq!(db.log.get(), [1]);
+
+    drop(fs::remove_dir_all(dir));
+}
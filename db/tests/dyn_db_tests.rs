@@ -0,0 +1,273 @@
+use db_rs::descriptor::TableKind;
+use db_rs::{Config, Db, DbError, DynDb, List, LookupTable};
+use std::fs;
+
+#[test]
+fn tables_are_reopened() {
+    let dir = "/tmp/dyn_db1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    db.lookup::<String, u64>("word_counts")
+        .unwrap()
+        .insert("hello".into(), 1)
+        .unwrap();
+    db.single::<String>("owner")
+        .unwrap()
+        .insert("parth".into())
+        .unwrap();
+    db.list::<u32>("log").unwrap().push(7).unwrap();
+    drop(db);
+    assert!(fs::metadata(format!("{dir}dyn_db.db")).is_ok());
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    let ids: Vec<_> = db
+        .registered()
+        .iter()
+        .map(|table| (table.name.as_str(), table.id))
+        .collect();
+    assert_eq!(ids, [("word_counts", 2), ("owner", 3), ("log", 4)]);
+
+    assert_eq!(db.list::<u32>("log").unwrap().get(), [7]);
+    assert_eq!(db.single::<String>("owner").unwrap().get().unwrap(), "parth");
+    let counts = db.lookup::<String, u64>("word_counts").unwrap();
+    assert_eq!(counts.get().get("hello"), Some(&1));
+
+    db.lookup_set::<u8, u8>("tags").unwrap();
+    assert_eq!(db.registered().last().unwrap().id, 5);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn types_are_checked() {
+    let dir = "/tmp/dyn_db2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    db.lookup::<String, u64>("word_counts").unwrap();
+    let err = db.lookup::<String, i64>("word_counts").unwrap_err();
+    let DbError::TableKindMismatch { table, registered, requested } = err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(table, "word_counts");
+    assert_eq!(
+        *registered,
+        TableKind::LookupTable { key: "alloc::string::String".into(), value: "u64".into() }
+    );
+    assert_eq!(
+        *requested,
+        TableKind::LookupTable { key: "alloc::string::String".into(), value: "i64".into() }
+    );
+    drop(db);
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    assert!(matches!(
+        db.lookup_list::<String, u64>("word_counts"),
+        Err(DbError::TableKindMismatch { .. })
+    ));
+    assert!(matches!(
+        db.lookup::<u64, String>("word_counts"),
+        Err(DbError::TableKindMismatch { .. })
+    ));
+    assert!(db.lookup::<String, u64>("word_counts").is_ok());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+mod before {
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
+    pub struct Word(pub String);
+}
+
+mod after {
+    #[derive(serde::Serialize, serde::Deserialize, Clone)]
+    pub struct Word(pub String);
+}
+
+#[test]
+fn moved_types_can_be_reopened() {
+    let dir = "/tmp/dyn_db6/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    db.lookup::<u8, before::Word>("words")
+        .unwrap()
+        .insert(1, before::Word("hello".into()))
+        .unwrap();
+    drop(db);
+
+    // the type names differ, so the move has to be recorded
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    assert!(matches!(
+        db.lookup::<u8, after::Word>("words"),
+        Err(DbError::TableKindMismatch { .. })
+    ));
+    assert!(matches!(
+        db.retype::<List<after::Word>>("words"),
+        Err(DbError::TableKindMismatch { .. })
+    ));
+    let words = db.retype::<LookupTable<u8, after::Word>>("words").unwrap();
+    assert_eq!(words.get()[&1].0, "hello");
+    assert_eq!(
+        db.registered()[0].kind,
+        TableKind::LookupTable { key: "u8".into(), value: "dyn_db_tests::after::Word".into() }
+    );
+    drop(db);
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    assert!(matches!(
+        db.lookup::<u8, before::Word>("words"),
+        Err(DbError::TableKindMismatch { .. })
+    ));
+    assert_eq!(db.lookup::<u8, after::Word>("words").unwrap().get()[&1].0, "hello");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn unreadable_entries_are_reported() {
+    let dir = "/tmp/dyn_db8/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    db.lookup::<u8, u64>("counts")
+        .unwrap()
+        .insert(1, 1)
+        .unwrap();
+    drop(db);
+
+    // the values were u64s, which don't deserialize as strings
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    let err = db.retype::<LookupTable<u8, String>>("counts").unwrap_err();
+    assert!(err.is_corrupt());
+    let DbError::CorruptEntry { table_id, table, .. } = err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!((table_id, table.as_ref()), (2, "counts"));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compact_on_quota() {
+    let dir = "/tmp/dyn_db7/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.max_log_size = Some(400);
+    config.compact_on_quota = true;
+
+    let mut db = DynDb::init(config.clone()).unwrap();
+    for i in 0..100 {
+        db.lookup::<u8, u64>("counts")
+            .unwrap()
+            .insert(1, i)
+            .unwrap();
+    }
+    db.list::<u8>("log").unwrap().push(1).unwrap();
+    drop(db);
+
+    // counts isn't open yet, so its compacted entries are carried over as they are
+    let mut db = DynDb::init(config).unwrap();
+    for i in 0..100 {
+        db.single::<u64>("counter").unwrap().insert(i).unwrap();
+    }
+    assert_eq!(db.lookup::<u8, u64>("counts").unwrap().get()[&1], 99);
+    assert_eq!(db.list::<u8>("log").unwrap().get(), [1]);
+    assert_eq!(db.single::<u64>("counter").unwrap().get(), Some(&99));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn unopened_tables_survive_compaction() {
+    let dir = "/tmp/dyn_db3/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    for i in 0..10 {
+        db.single::<u32>("counter").unwrap().insert(i).unwrap();
+    }
+    db.list::<u32>("log").unwrap().push(1).unwrap();
+    drop(db);
+
+    // only the counter is opened, the log's entries are carried over untouched
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.single::<u32>("counter").unwrap().get(), Some(&9));
+    db.compact_log().unwrap();
+    drop(db);
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.list::<u32>("log").unwrap().get(), [1]);
+    assert_eq!(db.single::<u32>("counter").unwrap().get(), Some(&9));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn transactions_and_snapshots() {
+    let dir = "/tmp/dyn_db4/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.fs_locks = false;
+
+    let mut db = DynDb::init(config.clone()).unwrap();
+    db.list::<u32>("log").unwrap().push(1).unwrap();
+    let snapshot = db.snapshot().unwrap();
+
+    let tx = db.begin_transaction().unwrap();
+    db.list::<u32>("log").unwrap().push(2).unwrap();
+    db.lookup::<u8, String>("names")
+        .unwrap()
+        .insert(1, "one".into())
+        .unwrap();
+    {
+        let mut db = DynDb::init(config.clone()).unwrap();
+        assert_eq!(db.registered().len(), 1);
+        assert_eq!(db.list::<u32>("log").unwrap().get(), [1]);
+    }
+    drop(tx);
+
+    assert_eq!(snapshot.get::<List<u32>>("log").unwrap().get(), [1]);
+    assert!(snapshot.get::<LookupTable<u8, String>>("names").is_none());
+    assert!(snapshot.get::<List<u64>>("log").is_none());
+
+    let mut db = DynDb::init(config).unwrap();
+    assert_eq!(db.list::<u32>("log").unwrap().get(), [1, 2]);
+    assert_eq!(
+        db.lookup::<u8, String>("names")
+            .unwrap()
+            .get()
+            .get(&1)
+            .unwrap(),
+        "one"
+    );
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn ids_past_the_narrow_limit() {
+    let dir = "/tmp/dyn_db5/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = DynDb::init(Config::in_folder(dir)).unwrap();
+    for i in 0..253 {
+        db.single::<u32>(&format!("table{i}")).unwrap();
+    }
+    assert_eq!(db.registered().last().unwrap().id, 254);
+    assert!(matches!(db.single::<u32>("one_more"), Err(DbError::WideTableId { id: 255 })));
+    drop(db);
+
+    let mut config = Config::in_folder(dir);
+    config.wide_table_ids = true;
+    let mut db = DynDb::init(config.clone()).unwrap();
+    db.single::<u32>("one_more").unwrap().insert(3).unwrap();
+    drop(db);
+
+    let mut db = DynDb::init(config).unwrap();
+    assert_eq!(db.single::<u32>("one_more").unwrap().get(), Some(&3));
+    assert_eq!(db.registered().last().unwrap().id, 255);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,173 @@
+use db_rs::{Config, Db, DbError, List, Logger, LookupTable};
+use db_rs_derive::Schema;
+use std::fs;
+use std::path::Path;
+
+#[derive(Schema)]
+struct ErrorTest {
+    table1: LookupTable<u8, String>,
+    table2: LookupTable<u8, u64>,
+}
+
+fn log_path(dir: &str) -> std::path::PathBuf {
+    Path::new(dir).join("ErrorTest.db")
+}
+
+#[test]
+fn locked() {
+    let dir = "/tmp/errors1/";
+    drop(fs::remove_dir_all(dir));
+    let _db = ErrorTest::init(Config::in_folder(dir)).unwrap();
+
+    let err = ErrorTest::init(Config::in_folder(dir)).err().unwrap();
+    assert!(err.is_locked());
+    assert!(!err.is_corrupt());
+    assert!(matches!(err, DbError::Locked { path } if path == log_path(dir)));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn unsupported_version() {
+    let dir = "/tmp/errors2/";
+    drop(fs::remove_dir_all(dir));
+    fs::create_dir_all(dir).unwrap();
+    fs::write(log_path(dir), [9, 0]).unwrap();
+
+    let err = ErrorTest::init(Config::in_folder(dir)).err().unwrap();
+    assert!(matches!(
+        err,
+        DbError::UnsupportedVersion { expected: 4, found: 9, ref path } if *path == log_path(dir)
+    ));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn truncated_metadata() {
+    let dir = "/tmp/errors3/";
+    drop(fs::remove_dir_all(dir));
+    fs::create_dir_all(dir).unwrap();
+    fs::write(log_path(dir), [2]).unwrap();
+
+    let err = ErrorTest::init(Config::in_folder(dir)).err().unwrap();
+    assert!(err.is_corrupt());
+    assert!(matches!(err, DbError::Corrupt { offset: 0, .. }));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn corrupt_entry() {
+    let dir = "/tmp/errors4/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = ErrorTest::init(Config::in_folder(dir)).unwrap();
+    db.table1.insert(1, "a".to_string()).unwrap();
+    drop(db);
+
+    // an entry for table2 whose enum variant doesn't exist
+    let mut log = fs::read(log_path(dir)).unwrap();
+    let offset = log.len() as u64;
+    log.extend([2, 0, 0, 0, 4, 9, 9, 9, 9]);
+    fs::write(log_path(dir), log).unwrap();
+
+    let err = ErrorTest::init(Config::in_folder(dir)).err().unwrap();
+    assert!(err.is_corrupt());
+    match err {
+        DbError::CorruptEntry { table_id, table, offset: found, .. } => {
+            assert_eq!(table_id, 2);
+            assert_eq!(table, "table2");
+            assert_eq!(found, offset);
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn schema_name_missing() {
+    let dir = "/tmp/errors5/";
+    drop(fs::remove_dir_all(dir));
+
+    let err = Logger::init(Config::in_folder(dir)).err().unwrap();
+    assert!(matches!(err, DbError::SchemaNameMissing));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn snapshot_errors() {
+    let dir = "/tmp/errors6/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = ErrorTest::init(Config::in_folder(dir)).unwrap();
+
+    let tx = db.begin_transaction().unwrap();
+    assert!(matches!(db.snapshot().err().unwrap(), DbError::TransactionInProgress));
+    tx.drop_safely().unwrap();
+    drop(tx);
+
+    let snapshot = db.snapshot().unwrap();
+    db.compact_log().unwrap();
+    assert!(matches!(snapshot.compact_log().err().unwrap(), DbError::SnapshotOutdated));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[derive(Schema)]
+struct ReplayBounds {
+    list: List<u8>,
+}
+
+#[test]
+fn out_of_bounds_entry() {
+    let dir = "/tmp/errors7/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = ReplayBounds::init(Config::in_folder(dir)).unwrap();
+    db.list.push(1).unwrap();
+    drop(db);
+
+    // a Remove(5) for a list of one element
+    let path = Path::new(dir).join("ReplayBounds.db");
+    let mut log = fs::read(&path).unwrap();
+    let mut entry = 2_u32.to_le_bytes().to_vec();
+    entry.extend(5_u64.to_le_bytes());
+    log.push(1);
+    log.extend((entry.len() as u32).to_be_bytes());
+    log.extend(entry);
+    fs::write(&path, log).unwrap();
+
+    let err = ReplayBounds::init(Config::in_folder(dir)).err().unwrap();
+    assert!(err.is_corrupt());
+    match err {
+        DbError::CorruptEntry { table, source, .. } if table == "list" => {
+            assert!(matches!(*source, DbError::IndexOutOfBounds { index: 5, len: 1 }))
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[derive(Schema)]
+#[schema(name = "ErrorTest")]
+struct TableRemoved {
+    table1: LookupTable<u8, String>,
+}
+
+#[test]
+fn unknown_table() {
+    let dir = "/tmp/errors8/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = ErrorTest::init(Config::in_folder(dir)).unwrap();
+    db.table1.insert(1, "a".to_string()).unwrap();
+    let offset = fs::metadata(log_path(dir)).unwrap().len();
+    db.table2.insert(1, 1).unwrap();
+    drop(db);
+
+    let err = TableRemoved::init(Config::in_folder(dir)).err().unwrap();
+    assert!(err.is_corrupt());
+    assert!(matches!(err, DbError::UnknownTable { table_id: 2, offset: found } if found == offset));
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,123 @@
+use db_rs::{Config, Db, FlushPolicy, List};
+use db_rs_derive::Schema;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Schema)]
+struct FlushTest {
+    list: List<u64>,
+}
+
+fn buffered(dir: &str, max_bytes: usize, max_delay: Option<Duration>) -> Config {
+    let mut config = Config::in_folder(dir);
+    config.flush_policy = FlushPolicy::Buffered { max_bytes, max_delay };
+    config
+}
+
+fn log_size<D: Db>(db: &D) -> u64 {
+    fs::metadata(db.config().unwrap().db_location_v2().unwrap())
+        .unwrap()
+        .len()
+}
+
+#[test]
+fn explicit_flush() {
+    let dir = "/tmp/flush1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = FlushTest::init(buffered(dir, usize::MAX, None)).unwrap();
+    let empty = log_size(&db);
+
+    for i in 0..100 {
+        db.list.push(i).unwrap();
+    }
+    assert_eq!(log_size(&db), empty);
+
+    db.flush().unwrap();
+    assert!(log_size(&db) > empty);
+
+    drop(db);
+    let db = FlushTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.list.get().len(), 100);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn flush_on_drop() {
+    let dir = "/tmp/flush2/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = FlushTest::init(buffered(dir, usize::MAX, None)).unwrap();
+    let tx = db.begin_transaction().unwrap();
+    db.list.push(1).unwrap();
+    drop(tx);
+    db.list.push(2).unwrap();
+    drop(db);
+
+    let db = FlushTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.list.get(), [1, 2]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn flush_thresholds() {
+    let dir = "/tmp/flush3/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = FlushTest::init(buffered(dir, 100, None)).unwrap();
+    let empty = log_size(&db);
+    db.list.push(1).unwrap();
+    assert_eq!(log_size(&db), empty);
+    for i in 0..10 {
+        db.list.push(i).unwrap();
+    }
+    assert!(log_size(&db) > empty);
+    drop(db);
+    drop(fs::remove_dir_all(dir));
+
+    let mut db =
+        FlushTest::init(buffered(dir, usize::MAX, Some(Duration::from_millis(50)))).unwrap();
+    let empty = log_size(&db);
+    db.list.push(1).unwrap();
+    assert_eq!(log_size(&db), empty);
+    thread::sleep(Duration::from_millis(60));
+    db.list.push(2).unwrap();
+    assert!(log_size(&db) > empty);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn flush_after_delay_without_writes() {
+    let dir = "/tmp/flush5/";
+    drop(fs::remove_dir_all(dir));
+    let mut db =
+        FlushTest::init(buffered(dir, usize::MAX, Some(Duration::from_millis(50)))).unwrap();
+    let empty = log_size(&db);
+    db.list.push(1).unwrap();
+    assert_eq!(log_size(&db), empty);
+
+    thread::sleep(Duration::from_millis(200));
+    assert!(log_size(&db) > empty);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compaction_includes_buffered_writes() {
+    let dir = "/tmp/flush4/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = FlushTest::init(buffered(dir, usize::MAX, None)).unwrap();
+    db.list.push(1).unwrap();
+    let snapshot = db.snapshot().unwrap();
+    db.list.push(2).unwrap();
+    snapshot.compact_log().unwrap();
+    db.list.push(3).unwrap();
+    drop(snapshot);
+    drop(db);
+
+    let db = FlushTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.list.get(), [1, 2, 3]);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,93 @@
+use db_rs::{Config, Db, List, LookupTable, Single};
+use db_rs_derive::Schema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Schema)]
+struct Cache<K, V>
+where
+    K: Hash + Eq + Serialize,
+    V: Serialize + DeserializeOwned,
+{
+    entries: LookupTable<K, V>,
+    order: List<V>,
+}
+
+#[derive(Default)]
+struct Hits(Arc<AtomicU64>);
+
+#[derive(Schema)]
+struct WithRuntime<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    #[schema(skip)]
+    memo: HashMap<u64, String>,
+    value: Single<T>,
+    #[schema(skip)]
+    hits: Hits,
+    count: Single<u64>,
+}
+
+#[test]
+fn generic_schema() {
+    let dir = "/tmp/generic1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut ints = Cache::<u64, u64>::init(Config::in_folder(format!("{dir}ints"))).unwrap();
+    ints.entries.insert(1, 10).unwrap();
+    ints.order.push(10).unwrap();
+    drop(ints);
+
+    let mut config = Config::in_folder(dir);
+    config.file_stem = Some("strings".into());
+    let mut strings = Cache::<String, String>::init(config.clone()).unwrap();
+    strings.entries.insert("a".into(), "b".into()).unwrap();
+    strings.compact_log().unwrap();
+    drop(strings);
+
+    let ints = Cache::<u64, u64>::init(Config::in_folder(format!("{dir}ints"))).unwrap();
+    assert_eq!(ints.entries.get()[&1], 10);
+    assert_eq!(ints.order.get(), [10]);
+    let strings = Cache::<String, String>::init(config).unwrap();
+    assert_eq!(strings.entries.get()["a"], "b");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn skipped_fields() {
+    let dir = "/tmp/generic2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = WithRuntime::<String>::init(Config::in_folder(dir)).unwrap();
+    assert!(db.memo.is_empty());
+    db.memo.insert(1, "one".into());
+    db.hits.0.fetch_add(1, Ordering::Relaxed);
+    db.value.insert("value".into()).unwrap();
+    db.count.insert(3).unwrap();
+
+    let snapshot = db.snapshot().unwrap();
+    assert!(snapshot.memo.is_empty());
+    assert_eq!(snapshot.count.get(), Some(&3));
+
+    // skipped fields don't take a table id, so they can come and go
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables.keys().copied().collect::<Vec<_>>(), [1, 2]);
+    db.compact_log().unwrap();
+    drop(snapshot);
+    drop(db);
+
+    let db = WithRuntime::<String>::init(Config::in_folder(dir)).unwrap();
+    assert!(db.memo.is_empty());
+    assert_eq!(db.hits.0.load(Ordering::Relaxed), 0);
+    assert_eq!(db.value.get().unwrap(), "value");
+    assert_eq!(db.count.get(), Some(&3));
+
+    drop(fs::remove_dir_all(dir));
+}
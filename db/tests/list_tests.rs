@@ -120,3 +120,29 @@ fn list_test_clear() {
     assert_eq!(db.list3.get(), ["e", "f", "g", "h"]);
     drop(fs::remove_dir_all(dir));
 }
+
+#[test]
+fn out_of_bounds() {
+    let dir = "/tmp/list_bounds/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = Schema::init(Config::in_folder(dir)).unwrap();
+
+    assert_eq!(db.list1.pop().unwrap(), None);
+    db.list1.clear().unwrap();
+    db.list1.push("a".to_string()).unwrap();
+    assert!(matches!(
+        db.list1.remove(1),
+        Err(db_rs::DbError::IndexOutOfBounds { index: 1, len: 1 })
+    ));
+    assert_eq!(db.list1.pop().unwrap(), Some("a".to_string()));
+    assert_eq!(db.list1.pop().unwrap(), None);
+
+    // only the push and the successful pop were logged
+    assert_eq!(db.log_stats().unwrap().writes_since_compaction, 2);
+
+    drop(db);
+    let db = Schema::init(Config::in_folder(dir)).unwrap();
+    assert!(db.list1.get().is_empty());
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -17,7 +17,10 @@ fn locks() {
     let dir = "/tmp/lock/";
     drop(fs::remove_dir_all(dir));
     let db = Schema::init(Config::in_folder(dir)).unwrap();
-    assert!(Schema::init(Config::in_folder(dir)).is_err());
+    assert!(Schema::init(Config::in_folder(dir))
+        .err()
+        .unwrap()
+        .is_locked());
     drop(db);
     drop(fs::remove_dir_all(dir));
 }
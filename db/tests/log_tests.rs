@@ -116,3 +116,96 @@ fn log_size<D: Db>(db: &D) -> usize {
 
     buf.len()
 }
+
+#[test]
+fn writes_after_compaction() {
+    let dir = "/tmp/fb";
+    drop(remove_dir_all(dir));
+
+    let mut db = LogTests::init(Config::in_folder(dir)).unwrap();
+    for i in 0..100 {
+        db.table1.insert(i, format!("{i}")).unwrap();
+    }
+    db.compact_log().unwrap();
+    db.table1.insert(100, "100".to_string()).unwrap();
+    db.compact_log().unwrap();
+    db.table1.insert(101, "101".to_string()).unwrap();
+    db.table2.insert(vec![1, 2, 3]).unwrap();
+    drop(db);
+
+    let db = LogTests::init(Config::in_folder(dir)).unwrap();
+    assert!(!db.incomplete_write().unwrap());
+    assert_eq!(db.table1.get().len(), 102);
+    assert_eq!(db.table1.get().get(&101).unwrap(), "101");
+    assert_eq!(db.table2.get().unwrap(), &vec![1, 2, 3]);
+
+    drop(remove_dir_all(dir));
+}
+
+#[test]
+fn extended_entry_size() {
+    let dir = "/tmp/fc";
+    drop(remove_dir_all(dir));
+    std::fs::create_dir_all(dir).unwrap();
+
+    let entry = bincode::serialize(&db_rs::lookup::LogEntry::Insert(5_u8, "five")).unwrap();
+    let mut log = vec![2, 0, 1, 255, 255, 255, 255];
+    log.extend((entry.len() as u64).to_be_bytes());
+    log.extend(entry);
+    std::fs::write(format!("{dir}/LogTests.db"), log).unwrap();
+
+    let db = LogTests::init(Config::in_folder(dir)).unwrap();
+    assert!(!db.incomplete_write().unwrap());
+    assert_eq!(db.table1.get().get(&5).unwrap(), "five");
+
+    drop(remove_dir_all(dir));
+}
+
+#[test]
+fn v1_logs_upgraded_by_compaction() {
+    let dir = "/tmp/fd";
+    drop(remove_dir_all(dir));
+    std::fs::create_dir_all(dir).unwrap();
+
+    let entry = bincode::serialize(&db_rs::lookup::LogEntry::Insert(5_u8, "five")).unwrap();
+    let mut log = vec![1, 0, 1];
+    log.extend((entry.len() as u32).to_be_bytes());
+    log.extend(entry);
+    std::fs::write(format!("{dir}/LogTests.db"), log).unwrap();
+
+    let mut db = LogTests::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.table1.get().get(&5).unwrap(), "five");
+    db.table1.insert(6, "six".to_string()).unwrap();
+    db.compact_log().unwrap();
+    db.table1.insert(7, "seven".to_string()).unwrap();
+    drop(db);
+
+    let log = std::fs::read(format!("{dir}/LogTests.db")).unwrap();
+    assert_eq!(log[0], 2);
+
+    let db = LogTests::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.table1.get().len(), 3);
+
+    drop(remove_dir_all(dir));
+}
+
+#[test]
+fn read_only_open() {
+    let dir = "/tmp/read_only_open";
+    drop(remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.read_only = true;
+
+    // a missing log isn't created
+    assert!(matches!(LogTests::init(config.clone()), Err(db_rs::DbError::Io(_))));
+    assert!(!std::path::Path::new(dir).join("LogTests.db").exists());
+
+    let mut db = LogTests::init(Config::in_folder(dir)).unwrap();
+    db.table1.insert(1, "one".to_string()).unwrap();
+    drop(db);
+
+    let db = LogTests::init(config).unwrap();
+    assert_eq!(db.table1.get().get(&1).unwrap(), "one");
+
+    drop(remove_dir_all(dir));
+}
@@ -92,3 +92,23 @@ fn test3() {
 
     drop(fs::remove_dir_all(dir));
 }
+
+#[test]
+fn no_op_removes() {
+    let dir = "/tmp/lookup_list_bounds/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = LookupSchema::init(Config::in_folder(dir)).unwrap();
+
+    assert!(!db.table1.remove(&1, 0).unwrap());
+    db.table1.push(1, "a".to_string()).unwrap();
+    assert!(!db.table1.remove(&1, 1).unwrap());
+    assert_eq!(db.table1.clear_key(&2).unwrap(), None);
+    assert!(db.table1.remove(&1, 0).unwrap());
+    assert_eq!(db.log_stats().unwrap().writes_since_compaction, 2);
+
+    drop(db);
+    let db = LookupSchema::init(Config::in_folder(dir)).unwrap();
+    assert!(db.table1.get()[&1].is_empty());
+
+    drop(fs::remove_dir_all(dir));
+}
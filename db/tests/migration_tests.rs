@@ -28,6 +28,8 @@ fn migration_test() {
     // make sure the files we expect are there
     assert!(v2.exists());
     assert!(!v1.exists());
+    // the migrated entries keep their v1 framing
+    assert_eq!(fs::read(&v2).unwrap()[0], 1);
 
     // make sure the data we expect is there
     assert_eq!(db.table.get().len(), 4);
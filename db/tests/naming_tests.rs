@@ -0,0 +1,87 @@
+use db_rs::{Config, Db, DbError, Single};
+use db_rs_derive::Schema;
+use std::fs;
+use std::path::Path;
+
+#[derive(Schema)]
+#[schema(name = "accounts")]
+struct RenamedSchema {
+    owner: Single<String>,
+}
+
+#[derive(Schema)]
+struct NamingTest {
+    owner: Single<String>,
+}
+
+#[test]
+fn schema_name_attribute() {
+    let dir = "/tmp/naming1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = RenamedSchema::init(Config::in_folder(dir)).unwrap();
+    db.owner.insert("parth".into()).unwrap();
+    drop(db);
+
+    assert!(Path::new(dir).join("accounts.db").exists());
+    assert!(!Path::new(dir).join("RenamedSchema.db").exists());
+    let db = RenamedSchema::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.owner.get().unwrap(), "parth");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn file_stem_override() {
+    let dir = "/tmp/naming2/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.file_stem = Some("accounts".into());
+
+    // the file stem wins over the schema's name
+    let mut db = NamingTest::init(config.clone()).unwrap();
+    db.owner.insert("parth".into()).unwrap();
+    db.compact_log().unwrap();
+    drop(db);
+    assert!(Path::new(dir).join("accounts.db").exists());
+    assert!(!Path::new(dir).join("NamingTest.db").exists());
+
+    let db = RenamedSchema::init(config).unwrap();
+    assert_eq!(db.owner.get().unwrap(), "parth");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn guard_new_db() {
+    let dir = "/tmp/naming3/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.guard_new_db = true;
+
+    // nothing else in the folder
+    let mut db = RenamedSchema::init(config.clone()).unwrap();
+    db.owner.insert("parth".into()).unwrap();
+    drop(db);
+
+    // as if RenamedSchema was renamed to NamingTest
+    let err = NamingTest::init(config.clone()).err().unwrap();
+    match err {
+        DbError::OtherLogsExist { path, others } => {
+            assert_eq!(path, Path::new(dir).join("NamingTest.db"));
+            assert_eq!(others, [Path::new(dir).join("accounts.db")]);
+        }
+        _ => panic!("expected OtherLogsExist"),
+    }
+    assert!(!Path::new(dir).join("NamingTest.db").exists());
+
+    // existing logs open as usual
+    let db = RenamedSchema::init(config).unwrap();
+    assert_eq!(db.owner.get().unwrap(), "parth");
+    drop(db);
+
+    // unless the guard is off
+    NamingTest::init(Config::in_folder(dir)).unwrap();
+    assert!(Path::new(dir).join("NamingTest.db").exists());
+
+    drop(fs::remove_dir_all(dir));
+}
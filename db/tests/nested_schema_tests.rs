@@ -0,0 +1,122 @@
+use db_rs::{Config, Db, List, LookupTable, Single, Tables};
+use db_rs_derive::Schema;
+use std::fs;
+
+mod files {
+    use db_rs::{List, LookupTable};
+    use db_rs_derive::Schema;
+
+    #[derive(Schema)]
+    pub struct Files {
+        pub docs: LookupTable<u64, String>,
+        pub trash: List<u64>,
+    }
+}
+
+#[derive(Schema)]
+struct Billing {
+    plan: Single<String>,
+}
+
+#[derive(Schema)]
+struct App {
+    users: LookupTable<u64, String>,
+    #[schema(nested)]
+    files: files::Files,
+    #[schema(nested)]
+    billing: Billing,
+    version: Single<u32>,
+}
+
+#[derive(Schema)]
+struct Root {
+    #[schema(nested)]
+    app: App,
+    extra: List<u8>,
+}
+
+#[test]
+fn nested_tables_take_the_next_ids() {
+    let dir = "/tmp/nested1/";
+    drop(fs::remove_dir_all(dir));
+
+    assert_eq!(files::Files::TABLE_COUNT, 2);
+    assert_eq!(App::TABLE_COUNT, 5);
+    assert_eq!(Root::TABLE_COUNT, 6);
+
+    let mut db = Root::init(Config::in_folder(dir)).unwrap();
+    db.app.users.insert(1, "parth".into()).unwrap();
+    db.app.files.docs.insert(7, "notes.md".into()).unwrap();
+    db.app.files.trash.push(8).unwrap();
+    db.app.billing.plan.insert("premium".into()).unwrap();
+    db.app.version.insert(2).unwrap();
+    db.extra.push(1).unwrap();
+
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables.keys().copied().collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6]);
+    drop(db);
+
+    let db = Root::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.app.users.get()[&1], "parth");
+    assert_eq!(db.app.files.docs.get()[&7], "notes.md");
+    assert_eq!(db.app.files.trash.get(), [8]);
+    assert_eq!(db.app.billing.plan.get().unwrap(), "premium");
+    assert_eq!(db.app.version.get(), Some(&2));
+    assert_eq!(db.extra.get(), [1]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn nested_schemas_share_the_log() {
+    let dir = "/tmp/nested2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = App::init(Config::in_folder(dir)).unwrap();
+    for i in 0..10 {
+        db.files.docs.insert(i, format!("doc {i}")).unwrap();
+        db.files.docs.remove(&i).unwrap();
+    }
+
+    let tx = db.begin_transaction().unwrap();
+    db.users.insert(1, "travis".into()).unwrap();
+    db.files.docs.insert(1, "readme".into()).unwrap();
+    db.billing.plan.insert("free".into()).unwrap();
+    drop(tx);
+
+    let snapshot = db.snapshot().unwrap();
+    assert_eq!(snapshot.files.docs.get()[&1], "readme");
+    assert_eq!(snapshot.billing.plan.get().unwrap(), "free");
+    drop(snapshot);
+
+    let before = db.log_stats().unwrap().log_size;
+    db.compact_log().unwrap();
+    assert!(db.log_stats().unwrap().log_size < before);
+    drop(db);
+
+    let db = App::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.users.get()[&1], "travis");
+    assert_eq!(db.files.docs.get().len(), 1);
+    assert_eq!(db.files.docs.get()[&1], "readme");
+    assert_eq!(db.billing.plan.get().unwrap(), "free");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn nested_schemas_are_schemas_of_their_own() {
+    let dir = "/tmp/nested3/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = files::Files::init(Config::in_folder(dir)).unwrap();
+    db.docs.insert(1, "standalone".into()).unwrap();
+    db.trash.push(1).unwrap();
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables.keys().copied().collect::<Vec<_>>(), [1, 2]);
+    drop(db);
+
+    let db = files::Files::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.docs.get()[&1], "standalone");
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,124 @@
+use db_rs::{Config, Db, DbError, Single};
+use db_rs_derive::Schema;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Schema)]
+struct QuotaTest {
+    counter: Single<u64>,
+}
+
+#[test]
+fn writes_fail_past_quota() {
+    let dir = "/tmp/quota1/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.max_log_size = Some(200);
+    let mut db = QuotaTest::init(config).unwrap();
+
+    let mut written = 0;
+    let err = loop {
+        match db.counter.insert(written) {
+            Ok(_) => written += 1,
+            Err(err) => break err,
+        }
+    };
+    assert!(matches!(err, DbError::QuotaExceeded { max: 200, .. }));
+    assert!(written > 0);
+    assert!(db.log_stats().unwrap().log_size <= 200);
+    assert_eq!(*db.counter.get().unwrap(), written - 1);
+
+    // compacting makes room again
+    db.compact_log().unwrap();
+    db.counter.insert(written).unwrap();
+    drop(db);
+
+    let db = QuotaTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(*db.counter.get().unwrap(), written);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn quota_counts_open_tx() {
+    let dir = "/tmp/quota2/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.max_log_size = Some(200);
+    let mut db = QuotaTest::init(config).unwrap();
+
+    let tx = db.begin_transaction().unwrap();
+    let err = (0..100)
+        .map(|i| db.counter.insert(i))
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(err, DbError::QuotaExceeded { .. }));
+    drop(tx);
+    assert!(db.log_stats().unwrap().log_size <= 200);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compact_on_quota() {
+    let dir = "/tmp/quota3/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.max_log_size = Some(200);
+    config.compact_on_quota = true;
+    let mut db = QuotaTest::init(config).unwrap();
+
+    for i in 0..100 {
+        db.counter.insert(i).unwrap();
+    }
+    assert!(db.log_stats().unwrap().log_size <= 200);
+    assert!(db.stats().unwrap().generation > 0);
+    drop(db);
+
+    let db = QuotaTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(*db.counter.get().unwrap(), 99);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compaction_needs_free_space() {
+    let dir = "/tmp/quota4/";
+    drop(fs::remove_dir_all(dir));
+    let mut config = Config::in_folder(dir);
+    config.min_free_space = Some(u64::MAX);
+    let mut db = QuotaTest::init(config).unwrap();
+    db.counter.insert(1).unwrap();
+
+    assert!(matches!(db.compact_log(), Err(DbError::InsufficientSpace { required: u64::MAX, .. })));
+    assert_eq!(db.stats().unwrap().generation, 0);
+    db.counter.insert(2).unwrap();
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn failed_compaction_removes_temp_file() {
+    let dir = "/tmp/quota5/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = QuotaTest::init(Config::in_folder(dir)).unwrap();
+    db.counter.insert(1).unwrap();
+
+    let result = db.get_logger().compact_log(|out| {
+        out.write_all(&[0; 64])?;
+        Err(DbError::Io(io::Error::new(io::ErrorKind::StorageFull, "no space left on device")))
+    });
+    match result {
+        Err(DbError::DiskFull { path }) => assert!(!path.exists()),
+        _ => panic!("expected DiskFull"),
+    }
+    assert!(!Path::new(dir).join("QuotaTest.db.tmp").exists());
+
+    db.counter.insert(2).unwrap();
+    drop(db);
+    let db = QuotaTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(*db.counter.get().unwrap(), 2);
+
+    drop(fs::remove_dir_all(dir));
+}
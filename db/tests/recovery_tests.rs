@@ -0,0 +1,102 @@
+use db_rs::{Config, Db, DbError, List};
+use db_rs_derive::Schema;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Schema)]
+struct RecoveryTest {
+    list: List<u64>,
+}
+
+#[test]
+fn poisoned_after_io_error() {
+    let dir = "/tmp/recovery1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = RecoveryTest::init(Config::in_folder(dir)).unwrap();
+    db.list.push(1).unwrap();
+    drop(db);
+
+    // writes to a read only log fail
+    let mut config = Config::in_folder(dir);
+    config.read_only = true;
+    let mut db = RecoveryTest::init(config).unwrap();
+    assert!(matches!(db.list.push(2), Err(DbError::Io(_))));
+    assert!(db.stats().unwrap().failure.is_some());
+
+    assert!(matches!(db.list.push(2), Err(DbError::LogPoisoned { .. })));
+    assert!(matches!(db.flush(), Err(DbError::LogPoisoned { .. })));
+    assert!(matches!(db.compact_log(), Err(DbError::LogPoisoned { .. })));
+    {
+        let tx = db.begin_transaction().unwrap();
+        assert!(matches!(tx.drop_safely(), Err(DbError::LogPoisoned { .. })));
+    }
+    {
+        // dropping the handle of a tx that can't be logged doesn't panic
+        let _tx = db.begin_transaction().unwrap();
+        db.list.push(3).unwrap_err();
+    }
+    assert_eq!(db.list.get(), [1]);
+
+    db.recover().unwrap();
+    assert!(db.stats().unwrap().failure.is_none());
+    assert_eq!(db.list.get(), [1]);
+    assert!(matches!(db.list.push(2), Err(DbError::Io(_))));
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn recover_truncates_partial_entry() {
+    let dir = "/tmp/recovery2/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = RecoveryTest::init(Config::in_folder(dir)).unwrap();
+    db.list.push(1).unwrap();
+    db.list.push(2).unwrap();
+    let size = db.log_stats().unwrap().log_size;
+
+    // the start of an entry that claims to be 100 bytes long
+    let path = Path::new(dir).join("RecoveryTest.db");
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&[1, 0, 0, 0, 100, 1, 2, 3]).unwrap();
+    drop(file);
+
+    db.recover().unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), size);
+    assert_eq!(db.list.get(), [1, 2]);
+    assert_eq!(db.stats().unwrap().tables[&1].entries, 2);
+
+    db.list.push(3).unwrap();
+    drop(db);
+    let db = RecoveryTest::init(Config::in_folder(dir)).unwrap();
+    assert!(!db.incomplete_write().unwrap());
+    assert_eq!(db.list.get(), [1, 2, 3]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[derive(Schema)]
+struct WithCache {
+    list: List<u64>,
+    #[schema(nested)]
+    inner: RecoveryTest,
+    #[schema(skip)]
+    cache: Vec<u64>,
+}
+
+#[test]
+fn recover_keeps_skipped_fields() {
+    let dir = "/tmp/recovery3/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = WithCache::init(Config::in_folder(dir)).unwrap();
+    db.list.push(1).unwrap();
+    db.inner.list.push(2).unwrap();
+    db.cache = vec![1, 2];
+
+    db.recover().unwrap();
+    assert_eq!(db.cache, [1, 2]);
+    assert_eq!(db.list.get(), [1]);
+    assert_eq!(db.inner.list.get(), [2]);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,107 @@
+use db_rs::reflect::{Reflect, TableInfo};
+use db_rs::table::Table;
+use db_rs::{Config, Db, List, LookupSet, LookupTable, Single};
+use db_rs_derive::Schema;
+use std::fs;
+
+#[derive(Schema)]
+#[schema(reflect)]
+struct Files {
+    docs: LookupTable<u64, String>,
+    tags: LookupSet<u64, String>,
+}
+
+#[derive(Schema)]
+#[schema(reflect)]
+struct App {
+    owner: Single<String>,
+    #[schema(nested)]
+    files: Files,
+    #[schema(skip)]
+    cache: Vec<u8>,
+    log: List<u32>,
+}
+
+#[test]
+fn tables_are_listed() {
+    let docs =
+        TableInfo { name: "docs", id: 1, type_name: "LookupTable<u64, String>", nested: &[] };
+    let tags = TableInfo { name: "tags", id: 2, type_name: "LookupSet<u64, String>", nested: &[] };
+    assert_eq!(Files::TABLES, [docs, tags]);
+
+    let names: Vec<_> = App::TABLES
+        .iter()
+        .map(|table| (table.name, table.id))
+        .collect();
+    assert_eq!(names, [("owner", 1), ("files", 2), ("log", 4)]);
+    assert_eq!(App::TABLES[1].type_name, "Files");
+    assert_eq!(App::TABLES[1].nested, Files::TABLES);
+    assert_eq!(App::TABLES[2].type_name, "List<u32>");
+}
+
+#[test]
+fn tables_are_visited() {
+    let dir = "/tmp/reflect1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = App::init(Config::in_folder(dir)).unwrap();
+    assert!(db.cache.is_empty());
+    db.owner.insert("parth".into()).unwrap();
+    db.files.docs.insert(1, "readme".into()).unwrap();
+    db.files.docs.insert(2, "notes".into()).unwrap();
+    db.log.push(1).unwrap();
+    db.log.push(2).unwrap();
+    db.log.push(3).unwrap();
+
+    let mut visited = vec![];
+    db.visit_tables(&mut |id, info, table| {
+        visited.push((id, info.name, table.len(), table.is_empty()));
+    });
+    assert_eq!(
+        visited,
+        [(1, "owner", 1, false), (2, "docs", 2, false), (3, "tags", 0, true), (4, "log", 3, false)]
+    );
+
+    let mut sizes = vec![];
+    db.visit_tables(&mut |_, _, table| sizes.push(table.serialized_size().unwrap()));
+    assert_eq!(sizes[0], db.owner.compact_repr().unwrap().len() as u64);
+    assert_eq!(sizes[1], db.files.docs.compact_repr().unwrap().len() as u64);
+    assert_eq!(sizes[2], 0);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+// cargo test -F json
+#[cfg(feature = "json")]
+mod json_feature {
+    use super::App;
+    use db_rs::reflect::Reflect;
+    use db_rs::{Config, Db};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn tables_are_dumped() {
+        let dir = "/tmp/reflect2/";
+        drop(fs::remove_dir_all(dir));
+
+        let mut db = App::init(Config::in_folder(dir)).unwrap();
+        db.files.docs.insert(7, "readme".into()).unwrap();
+        db.files.tags.insert(7, "pinned".into()).unwrap();
+        db.log.push(1).unwrap();
+
+        let mut dumps = vec![];
+        db.visit_tables(&mut |_, info, table| dumps.push((info.name, table.dump().unwrap())));
+        assert_eq!(
+            dumps,
+            [
+                ("owner", json!(null)),
+                ("docs", json!([[7, "readme"]])),
+                ("tags", json!([[7, ["pinned"]]])),
+                ("log", json!([1])),
+            ]
+        );
+
+        drop(fs::remove_dir_all(dir));
+    }
+}
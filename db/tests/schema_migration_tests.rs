@@ -0,0 +1,222 @@
+use db_rs::{Config, Db, DbError, LookupTable, Single};
+use db_rs_derive::Schema;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct AccountV1 {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Account {
+    first: String,
+    last: String,
+}
+
+#[derive(Schema)]
+struct SchemaV1 {
+    accounts: LookupTable<u64, AccountV1>,
+}
+
+#[derive(Schema)]
+#[schema(migrate_from = SchemaV1, with = v1_to_v2)]
+struct SchemaV2 {
+    accounts: LookupTable<u64, Account>,
+}
+
+#[derive(Schema)]
+#[schema(migrate_from = SchemaV2, with = v2_to_v3)]
+struct SchemaV3 {
+    accounts: LookupTable<u64, Account>,
+    next_id: Single<u64>,
+}
+
+thread_local! {
+    static MIGRATIONS: Cell<usize> = const { Cell::new(0) };
+    static FAIL: Cell<bool> = const { Cell::new(false) };
+}
+
+fn v1_to_v2(old: &SchemaV1, new: &mut SchemaV2) -> db_rs::DbResult<()> {
+    MIGRATIONS.with(|m| m.set(m.get() + 1));
+    if FAIL.with(Cell::get) {
+        return Err(DbError::SnapshotOutdated);
+    }
+    for (id, account) in old.accounts.get() {
+        let (first, last) = account.name.split_once(' ').unwrap_or((&account.name, ""));
+        new.accounts
+            .insert(*id, Account { first: first.into(), last: last.into() })?;
+    }
+    Ok(())
+}
+
+fn v2_to_v3(old: &SchemaV2, new: &mut SchemaV3) -> db_rs::DbResult<()> {
+    for (id, account) in old.accounts.get() {
+        new.accounts.insert(*id, account.clone())?;
+    }
+    let next_id = old
+        .accounts
+        .get()
+        .keys()
+        .max()
+        .map(|id| id + 1)
+        .unwrap_or(0);
+    new.next_id.insert(next_id)?;
+    Ok(())
+}
+
+fn write_v1(dir: &str) {
+    drop(fs::remove_dir_all(dir));
+    let mut db = SchemaV1::init(Config::in_folder(dir)).unwrap();
+    db.accounts
+        .insert(1, AccountV1 { name: "Ada Lovelace".into() })
+        .unwrap();
+    db.accounts
+        .insert(2, AccountV1 { name: "Grace Hopper".into() })
+        .unwrap();
+}
+
+fn assert_migrated(accounts: &LookupTable<u64, Account>) {
+    assert_eq!(accounts.get().len(), 2);
+    assert_eq!(accounts.get()[&1], Account { first: "Ada".into(), last: "Lovelace".into() });
+}
+
+#[test]
+fn migrates_once() {
+    let dir = "/tmp/schema-migration1/";
+    write_v1(dir);
+    MIGRATIONS.with(|m| m.set(0));
+
+    let db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    assert_migrated(&db.accounts);
+    assert!(!Path::new(dir).join("SchemaV1.db").exists());
+    assert!(Path::new(dir).join("SchemaV1.db.archived").exists());
+    drop(db);
+
+    let db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    assert_migrated(&db.accounts);
+    assert_eq!(MIGRATIONS.with(Cell::get), 1);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn interrupted_migration_is_not_repeated() {
+    let dir = "/tmp/schema-migration2/";
+    write_v1(dir);
+    MIGRATIONS.with(|m| m.set(0));
+
+    let mut db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    db.accounts
+        .insert(3, Account { first: "Alan".into(), last: "Turing".into() })
+        .unwrap();
+    // the marker survives compaction
+    db.compact_log().unwrap();
+    drop(db);
+
+    // as if the process stopped before the old log was archived
+    let old = Path::new(dir).join("SchemaV1.db");
+    fs::rename(Path::new(dir).join("SchemaV1.db.archived"), &old).unwrap();
+
+    let db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get().len(), 3);
+    assert_eq!(MIGRATIONS.with(Cell::get), 1);
+    assert!(!old.exists());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn failed_migration_leaves_old_log() {
+    let dir = "/tmp/schema-migration3/";
+    write_v1(dir);
+    FAIL.with(|f| f.set(true));
+
+    let err = SchemaV2::init(Config::in_folder(dir)).err().unwrap();
+    assert!(matches!(err, DbError::SnapshotOutdated));
+    assert!(!Path::new(dir).join("SchemaV2.db").exists());
+    assert!(!Path::new(dir).join("SchemaV2.db.migrating").exists());
+
+    let db = SchemaV1::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get().len(), 2);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn migrations_chain() {
+    let dir = "/tmp/schema-migration4/";
+    write_v1(dir);
+
+    let db = SchemaV3::init(Config::in_folder(dir)).unwrap();
+    assert_migrated(&db.accounts);
+    assert_eq!(db.next_id.get(), Some(&3));
+    assert!(Path::new(dir).join("SchemaV1.db.archived").exists());
+    assert!(Path::new(dir).join("SchemaV2.db.archived").exists());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn nothing_to_migrate() {
+    let dir = "/tmp/schema-migration5/";
+    drop(fs::remove_dir_all(dir));
+    MIGRATIONS.with(|m| m.set(0));
+
+    let mut db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    assert!(db.accounts.get().is_empty());
+    db.accounts
+        .insert(1, Account { first: "Ada".into(), last: "Lovelace".into() })
+        .unwrap();
+    drop(db);
+
+    // a new log isn't replaced by an old one that shows up later
+    write_v1_into(dir);
+    let db = SchemaV2::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get().len(), 1);
+    assert_eq!(MIGRATIONS.with(Cell::get), 0);
+    assert!(Path::new(dir).join("SchemaV1.db").exists());
+
+    drop(fs::remove_dir_all(dir));
+}
+
+fn write_v1_into(dir: &str) {
+    let mut db = SchemaV1::init(Config::in_folder(dir)).unwrap();
+    db.accounts
+        .insert(1, AccountV1 { name: "Someone Else".into() })
+        .unwrap();
+}
+
+fn listing(dir: &str) -> Vec<String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn read_only_migration_leaves_folder_untouched() {
+    let dir = "/tmp/schema-migration6/";
+    write_v1(dir);
+    MIGRATIONS.with(|m| m.set(0));
+    let before = listing(dir);
+
+    let mut config = Config::in_folder(dir);
+    config.read_only = true;
+    let err = SchemaV2::init(config).err().unwrap();
+    assert!(matches!(err, DbError::MigrationPending { from: "SchemaV1", to: "SchemaV2" }));
+    assert_eq!(listing(dir), before);
+
+    let mut config = Config::in_folder(dir);
+    config.create_db = false;
+    let err = SchemaV2::init(config).err().unwrap();
+    assert!(matches!(err, DbError::MigrationPending { .. }));
+    assert_eq!(listing(dir), before);
+    assert_eq!(MIGRATIONS.with(Cell::get), 0);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,108 @@
+use db_rs::{ConcurrentDb, Config, Db, List, LookupTable, Single};
+use db_rs_derive::Schema;
+use std::fs;
+use std::thread;
+
+#[derive(Schema)]
+struct SnapshotTest {
+    table1: LookupTable<u8, String>,
+    table2: Single<String>,
+    table3: List<u8>,
+}
+
+#[test]
+fn snapshot_is_unaffected_by_writes() {
+    let dir = "/tmp/snapshot1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = SnapshotTest::init(Config::in_folder(dir)).unwrap();
+    db.table1.insert(1, "one".to_string()).unwrap();
+    db.table2.insert("a".to_string()).unwrap();
+    db.table3.push(1).unwrap();
+
+    let snapshot = db.snapshot().unwrap();
+
+    db.table1.insert(1, "uno".to_string()).unwrap();
+    db.table1.insert(2, "dos".to_string()).unwrap();
+    db.table2.clear().unwrap();
+    db.table3.push(2).unwrap();
+
+    assert_eq!(snapshot.table1.get().len(), 1);
+    assert_eq!(snapshot.table1.get().get(&1).unwrap(), "one");
+    assert_eq!(snapshot.table2.get().unwrap(), "a");
+    assert_eq!(snapshot.table3.get(), [1]);
+
+    assert_eq!(db.table1.get().get(&1).unwrap(), "uno");
+    assert_eq!(db.table2.get(), None);
+    assert_eq!(db.table3.get(), [1, 2]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn no_snapshot_during_tx() {
+    let dir = "/tmp/snapshot2/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = SnapshotTest::init(Config::in_folder(dir)).unwrap();
+
+    let tx = db.begin_transaction().unwrap();
+    db.table3.push(1).unwrap();
+    assert!(db.snapshot().is_err());
+    drop(tx);
+    assert_eq!(db.snapshot().unwrap().table3.get(), [1]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn compact_from_snapshot_keeps_later_writes() {
+    let dir = "/tmp/snapshot3/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = SnapshotTest::init(Config::in_folder(dir)).unwrap();
+    for i in 0..100 {
+        db.table1.insert(1, format!("{i}")).unwrap();
+    }
+
+    let snapshot = db.snapshot().unwrap();
+    db.table1.insert(2, "after".to_string()).unwrap();
+    db.table3.push(7).unwrap();
+    snapshot.compact_log().unwrap();
+    db.table3.push(8).unwrap();
+
+    // a snapshot from before the last compaction can't be used to compact again
+    assert!(snapshot.compact_log().is_err());
+
+    // the snapshot keeps the source db's log open
+    drop(snapshot);
+    drop(db);
+    let db = SnapshotTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.table1.get().get(&1).unwrap(), "99");
+    assert_eq!(db.table1.get().get(&2).unwrap(), "after");
+    assert_eq!(db.table3.get(), [7, 8]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn snapshot_read_while_writing() {
+    let dir = "/tmp/snapshot4/";
+    drop(fs::remove_dir_all(dir));
+    let db = ConcurrentDb::<SnapshotTest>::init(Config::in_folder(dir)).unwrap();
+    db.transaction(|db| db.table3.push(0)).unwrap();
+
+    let snapshot = db.snapshot().unwrap();
+    let report = thread::spawn(move || snapshot.table3.get().to_vec());
+
+    for i in 1..50 {
+        db.transaction(|db| db.table3.push(i)).unwrap();
+    }
+    db.compact_log().unwrap();
+
+    assert_eq!(report.join().unwrap(), [0]);
+    assert_eq!(db.read().unwrap().table3.get().len(), 50);
+
+    drop(db);
+    let db = SnapshotTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.table3.get().len(), 50);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,85 @@
+use db_rs::{Config, Db, List, LookupTable, TableStats};
+use db_rs_derive::Schema;
+use std::fs;
+
+#[derive(Schema)]
+struct StatsTest {
+    lookup: LookupTable<u8, String>,
+    list: List<u64>,
+}
+
+#[test]
+fn table_stats() {
+    let dir = "/tmp/stats1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = StatsTest::init(Config::in_folder(dir)).unwrap();
+
+    for i in 0..10 {
+        db.lookup.insert(1, format!("{i}")).unwrap();
+    }
+    {
+        let tx = db.begin_transaction().unwrap();
+        for i in 0..3 {
+            db.list.push(i).unwrap();
+        }
+        tx.drop_safely().unwrap();
+    }
+
+    let stats = db.stats().unwrap();
+    let lookup = stats.tables[&1].clone();
+    let list = stats.tables[&2].clone();
+    assert_eq!(lookup.entries, 10);
+    assert_eq!(lookup.writes_since_compaction, 10);
+    assert_eq!(list.entries, 3);
+    assert_eq!(list.writes_since_compaction, 3);
+    assert!(lookup.bytes > list.bytes);
+    assert_eq!(stats.log.writes_since_compaction, 13);
+    assert_eq!(stats.generation, 0);
+
+    // replay counts the same entries, but nothing has been written since opening
+    drop(db);
+    let mut db = StatsTest::init(Config::in_folder(dir)).unwrap();
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables[&1], TableStats { writes_since_compaction: 0, ..lookup });
+    assert_eq!(stats.tables[&2], TableStats { writes_since_compaction: 0, ..list });
+
+    db.compact_log().unwrap();
+    db.list.push(3).unwrap();
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.generation, 1);
+    assert_eq!(stats.tables[&1].entries, 1);
+    assert_eq!(stats.tables[&1].writes_since_compaction, 0);
+    assert_eq!(stats.tables[&2].entries, 4);
+    assert_eq!(stats.tables[&2].writes_since_compaction, 1);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn writes_during_snapshot_compaction() {
+    let dir = "/tmp/stats2/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = StatsTest::init(Config::in_folder(dir)).unwrap();
+
+    for i in 0..5 {
+        db.list.push(i).unwrap();
+    }
+    let snapshot = db.snapshot().unwrap();
+    db.list.push(5).unwrap();
+    db.lookup.insert(1, "a".to_string()).unwrap();
+    snapshot.compact_log().unwrap();
+
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables[&2].entries, 6);
+    assert_eq!(stats.tables[&2].writes_since_compaction, 1);
+    assert_eq!(stats.tables[&1].entries, 1);
+    assert_eq!(stats.tables[&1].writes_since_compaction, 1);
+    assert_eq!(stats.log.writes_since_compaction, 2);
+
+    drop(snapshot);
+    drop(db);
+    let db = StatsTest::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.stats().unwrap().tables[&2].entries, 6);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,115 @@
+#[cfg(feature = "tracing")]
+mod tracing_feature {
+    use db_rs::{Config, Db, List, LookupTable};
+    use db_rs_derive::Schema;
+    use std::fmt::Debug;
+    use std::fs;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Schema)]
+    struct TracingTest {
+        table1: LookupTable<u8, String>,
+        table2: List<u8>,
+    }
+
+    /// Records the names of spans and the fields of events.
+    #[derive(Default, Clone)]
+    struct Recorder {
+        next_id: Arc<AtomicU64>,
+        spans: Arc<Mutex<Vec<String>>>,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0 += &format!("{}={:?} ", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.spans
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_string());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.events.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn spans_and_events() {
+        let dir = "/tmp/tracing1/";
+        drop(fs::remove_dir_all(dir));
+        let recorder = Recorder::default();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut db = TracingTest::init(Config::in_folder(dir)).unwrap();
+            let tx = db.begin_transaction().unwrap();
+            db.table1.insert(1, "a".to_string()).unwrap();
+            db.table2.push(1).unwrap();
+            tx.drop_safely().unwrap();
+            drop(tx);
+            db.compact_log().unwrap();
+            drop(db);
+
+            TracingTest::init(Config::in_folder(dir)).unwrap();
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        for name in
+            ["init", "lock_file", "handle_migration", "replay", "begin_tx", "end_tx", "write"]
+        {
+            assert!(spans.iter().any(|span| span == name), "missing span {name}");
+        }
+        assert!(spans.iter().any(|span| span == "compact_log_since"));
+
+        let events = recorder.events.lock().unwrap();
+        assert!(events.iter().any(|event| event.contains("committed tx")));
+        assert!(events
+            .iter()
+            .any(|event| event.contains("table=\"table2\"") && event.contains("entries=1")));
+
+        drop(fs::remove_dir_all(dir));
+    }
+
+    #[test]
+    fn errors_are_recorded() {
+        let dir = "/tmp/tracing2/";
+        drop(fs::remove_dir_all(dir));
+        let recorder = Recorder::default();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let _db = TracingTest::init(Config::in_folder(dir)).unwrap();
+            assert!(TracingTest::init(Config::in_folder(dir)).is_err());
+        });
+
+        let events = recorder.events.lock().unwrap();
+        assert!(events.iter().any(|event| event.contains("error=")));
+
+        drop(fs::remove_dir_all(dir));
+    }
+}
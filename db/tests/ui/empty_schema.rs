@@ -0,0 +1,9 @@
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct Accounts {}
+
+#[derive(Schema)]
+struct Unit;
+
+fn main() {}
@@ -0,0 +1,9 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+enum Accounts {
+    Owner(Single<String>),
+}
+
+fn main() {}
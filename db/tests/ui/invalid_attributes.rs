@@ -0,0 +1,27 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct AccountsV1 {
+    owner: Single<u16>,
+}
+
+#[derive(Schema)]
+#[schema(migrate_from = AccountsV1)]
+struct AccountsV2 {
+    owner: Single<u16>,
+}
+
+#[derive(Schema)]
+#[schema(name = "../accounts")]
+struct AccountsV3 {
+    owner: Single<u16>,
+}
+
+#[derive(Schema)]
+struct AccountsV4 {
+    #[table(upgrade = [u16])]
+    owner: Single<u16>,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+#[table(upgrade = [u8 -> u16])]
+struct Accounts {
+    owner: Single<u16>,
+}
+
+#[derive(Schema)]
+struct Users {
+    #[schema(name = "users")]
+    owner: Single<u16>,
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+use db_rs::{LookupTable, Single};
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct Files {
+    docs: LookupTable<u64, String>,
+}
+
+#[derive(Schema)]
+struct NotASchema {
+    owner: Single<u16>,
+    #[schema(nested)]
+    docs: LookupTable<u64, String>,
+}
+
+#[derive(Schema)]
+struct Upgraded {
+    owner: Single<u16>,
+    #[schema(nested)]
+    #[table(upgrade = [u8 -> u16])]
+    files: Files,
+}
+
+#[derive(Schema)]
+struct Both {
+    owner: Single<u16>,
+    #[schema(nested, skip)]
+    files: Files,
+}
+
+#[derive(Schema)]
+#[schema(nested)]
+struct Misplaced {
+    owner: Single<u16>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct Accounts {
+    name: String,
+    owner: Single<String>,
+}
+
+fn main() {}
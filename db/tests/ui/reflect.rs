@@ -0,0 +1,17 @@
+use db_rs::{LookupTable, Single};
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct Files {
+    docs: LookupTable<u64, String>,
+}
+
+#[derive(Schema)]
+#[schema(reflect)]
+struct App {
+    owner: Single<u16>,
+    #[schema(nested)]
+    files: Files,
+}
+
+fn main() {}
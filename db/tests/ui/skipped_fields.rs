@@ -0,0 +1,33 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+struct Handle;
+
+#[derive(Schema)]
+struct Accounts {
+    owner: Single<u16>,
+    #[schema(skip)]
+    handle: Handle,
+}
+
+#[derive(Schema)]
+struct Users {
+    owner: Single<u16>,
+    #[schema(skip)]
+    #[table(upgrade = [u8 -> u16])]
+    cache: Single<u16>,
+}
+
+#[derive(Schema)]
+struct Everything {
+    #[schema(skip)]
+    owner: Single<u16>,
+}
+
+#[derive(Schema)]
+#[schema(skip)]
+struct Nothing {
+    owner: Single<u16>,
+}
+
+fn main() {}
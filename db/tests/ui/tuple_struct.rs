@@ -0,0 +1,7 @@
+use db_rs::Single;
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+struct Accounts(Single<String>);
+
+fn main() {}
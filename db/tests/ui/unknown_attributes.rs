@@ -0,0 +1,16 @@
+use db_rs::{LookupTable, Single};
+use db_rs_derive::Schema;
+
+#[derive(Schema)]
+#[schema(nmae = "accounts")]
+struct Accounts {
+    owner: Single<String>,
+}
+
+#[derive(Schema)]
+struct Users {
+    #[table(upgrades = [u8 -> u16])]
+    ages: LookupTable<String, u16>,
+}
+
+fn main() {}
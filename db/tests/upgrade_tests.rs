@@ -0,0 +1,162 @@
+use db_rs::{Config, Db, DbError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountV0 {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountV1 {
+    name: String,
+    email: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Account {
+    name: String,
+    email: String,
+    admin: bool,
+}
+
+impl From<AccountV0> for AccountV1 {
+    fn from(v0: AccountV0) -> Self {
+        Self { name: v0.name, email: String::new() }
+    }
+}
+
+impl From<AccountV1> for Account {
+    fn from(v1: AccountV1) -> Self {
+        Self { name: v1.name, email: v1.email, admin: false }
+    }
+}
+
+mod v0 {
+    use super::*;
+    use db_rs::{List, LookupTable, Single};
+    use db_rs_derive::Schema;
+
+    #[derive(Schema)]
+    pub struct Accounts {
+        pub accounts: LookupTable<u64, AccountV0>,
+        pub owner: Single<AccountV0>,
+        pub log: List<AccountV0>,
+    }
+}
+
+mod v1 {
+    use super::*;
+    use db_rs::{List, LookupTable, Single};
+    use db_rs_derive::Schema;
+
+    #[derive(Schema)]
+    pub struct Accounts {
+        #[table(upgrade = [AccountV0 -> AccountV1])]
+        pub accounts: LookupTable<u64, AccountV1>,
+        pub owner: Single<AccountV0>,
+        pub log: List<AccountV0>,
+    }
+}
+
+mod v2 {
+    use super::*;
+    use db_rs::{List, LookupTable, Single};
+    use db_rs_derive::Schema;
+
+    #[derive(Schema)]
+    pub struct Accounts {
+        #[table(upgrade = [AccountV0 -> AccountV1 -> Account])]
+        pub accounts: LookupTable<u64, Account>,
+        #[table(upgrade = [AccountV0 -> AccountV1 -> Account])]
+        pub owner: Single<Account>,
+        #[table(upgrade = [AccountV0 -> AccountV1])]
+        pub log: List<AccountV1>,
+    }
+}
+
+mod v3 {
+    use super::*;
+    use db_rs::{List, LookupTable, Single};
+    use db_rs_derive::Schema;
+
+    #[derive(Schema)]
+    pub struct Accounts {
+        pub accounts: LookupTable<u64, Account>,
+        pub owner: Single<Account>,
+        pub log: List<AccountV1>,
+    }
+}
+
+fn v0(name: &str) -> AccountV0 {
+    AccountV0 { name: name.into() }
+}
+
+#[test]
+fn upgrades_through_versions() {
+    let dir = "/tmp/upgrade1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = v0::Accounts::init(Config::in_folder(dir)).unwrap();
+    db.accounts.insert(1, v0("ada")).unwrap();
+    db.accounts.insert(2, v0("grace")).unwrap();
+    db.owner.insert(v0("ada")).unwrap();
+    db.log.push(v0("ada")).unwrap();
+    drop(db);
+
+    let mut db = v1::Accounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get()[&1], AccountV1 { name: "ada".into(), email: String::new() });
+    db.accounts
+        .insert(2, AccountV1 { name: "grace".into(), email: "grace@navy.mil".into() })
+        .unwrap();
+    db.accounts.remove(&1).unwrap();
+    drop(db);
+
+    let mut db = v2::Accounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get().len(), 1);
+    assert_eq!(
+        db.accounts.get()[&2],
+        Account { name: "grace".into(), email: "grace@navy.mil".into(), admin: false }
+    );
+    assert_eq!(db.owner.get().unwrap().name, "ada");
+    assert_eq!(db.log.get(), [AccountV1 { name: "ada".into(), email: String::new() }]);
+
+    // compaction rewrites every entry with the current types
+    db.compact_log().unwrap();
+    drop(db);
+
+    let db = v3::Accounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get().len(), 1);
+    assert_eq!(db.owner.get().unwrap().name, "ada");
+    assert_eq!(db.log.get().len(), 1);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn without_upgrade_old_entries_are_corrupt() {
+    let dir = "/tmp/upgrade2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = v0::Accounts::init(Config::in_folder(dir)).unwrap();
+    db.accounts.insert(1, v0("ada")).unwrap();
+    drop(db);
+
+    let err = v3::Accounts::init(Config::in_folder(dir)).err().unwrap();
+    assert!(matches!(err, DbError::CorruptEntry { ref table, .. } if table == "accounts"));
+
+    // entries no version can read are reported against the current type
+    let mut log = fs::read(format!("{dir}Accounts.db")).unwrap();
+    log.extend([1, 0, 0, 0, 2, 9, 9]);
+    fs::write(format!("{dir}Accounts.db"), log).unwrap();
+    let err = v2::Accounts::init(Config::in_folder(dir)).err().unwrap();
+    match err {
+        DbError::CorruptEntry { table, source, .. } => {
+            assert_eq!(table, "accounts");
+            assert!(matches!(*source, DbError::Bincode(_)));
+        }
+        _ => panic!("expected CorruptEntry"),
+    }
+
+    drop(fs::remove_dir_all(dir));
+}
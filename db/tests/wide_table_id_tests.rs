@@ -0,0 +1,540 @@
+use db_rs::table::Table;
+use db_rs::{Config, Db, DbError, DbResult, Logger, LookupTable, Single, TableId};
+use db_rs_derive::Schema;
+use std::fs;
+
+#[derive(Schema)]
+struct Ten {
+    t0: Single<u32>,
+    t1: Single<u32>,
+    t2: Single<u32>,
+    t3: Single<u32>,
+    t4: Single<u32>,
+    t5: Single<u32>,
+    t6: Single<u32>,
+    t7: Single<u32>,
+    t8: Single<u32>,
+    t9: Single<u32>,
+}
+
+#[derive(Schema)]
+struct Hundred {
+    #[schema(nested)]
+    x0: Ten,
+    #[schema(nested)]
+    x1: Ten,
+    #[schema(nested)]
+    x2: Ten,
+    #[schema(nested)]
+    x3: Ten,
+    #[schema(nested)]
+    x4: Ten,
+    #[schema(nested)]
+    x5: Ten,
+    #[schema(nested)]
+    x6: Ten,
+    #[schema(nested)]
+    x7: Ten,
+    #[schema(nested)]
+    x8: Ten,
+    #[schema(nested)]
+    x9: Ten,
+}
+
+#[derive(Schema)]
+#[schema(name = "accounts")]
+struct Accounts {
+    accounts: LookupTable<u64, String>,
+}
+
+#[derive(Schema)]
+#[schema(name = "accounts", wide_table_ids)]
+struct ManyAccounts {
+    accounts: LookupTable<u64, String>,
+    #[schema(nested)]
+    h0: Hundred,
+    #[schema(nested)]
+    h1: Hundred,
+    #[schema(nested)]
+    h2: Hundred,
+    last: Single<String>,
+}
+
+/// a table without its own [Table::snapshot], so snapshots replay its compact repr
+struct Tally {
+    table_id: TableId,
+    count: u32,
+    logger: Logger,
+}
+
+impl Tally {
+    fn set(&mut self, count: u32) -> DbResult<()> {
+        self.logger
+            .write(self.table_id, bincode::serialize(&count)?)?;
+        self.count = count;
+        Ok(())
+    }
+}
+
+impl Table for Tally {
+    fn init(table_id: TableId, logger: Logger) -> Self {
+        Self { table_id, count: 0, logger }
+    }
+
+    fn handle_event(&mut self, bytes: &[u8]) -> DbResult<()> {
+        self.count = bincode::deserialize(bytes)?;
+        Ok(())
+    }
+
+    fn compact_repr(&self) -> DbResult<Vec<u8>> {
+        Ok(Logger::log_entry(self.table_id, bincode::serialize(&self.count)?))
+    }
+}
+
+// flat, so every id is a literal rather than an offset from a nested schema
+#[derive(Schema)]
+#[schema(wide_table_ids)]
+struct Flat {
+    t0: Single<u32>,
+    t1: Single<u32>,
+    t2: Single<u32>,
+    t3: Single<u32>,
+    t4: Single<u32>,
+    t5: Single<u32>,
+    t6: Single<u32>,
+    t7: Single<u32>,
+    t8: Single<u32>,
+    t9: Single<u32>,
+    t10: Single<u32>,
+    t11: Single<u32>,
+    t12: Single<u32>,
+    t13: Single<u32>,
+    t14: Single<u32>,
+    t15: Single<u32>,
+    t16: Single<u32>,
+    t17: Single<u32>,
+    t18: Single<u32>,
+    t19: Single<u32>,
+    t20: Single<u32>,
+    t21: Single<u32>,
+    t22: Single<u32>,
+    t23: Single<u32>,
+    t24: Single<u32>,
+    t25: Single<u32>,
+    t26: Single<u32>,
+    t27: Single<u32>,
+    t28: Single<u32>,
+    t29: Single<u32>,
+    t30: Single<u32>,
+    t31: Single<u32>,
+    t32: Single<u32>,
+    t33: Single<u32>,
+    t34: Single<u32>,
+    t35: Single<u32>,
+    t36: Single<u32>,
+    t37: Single<u32>,
+    t38: Single<u32>,
+    t39: Single<u32>,
+    t40: Single<u32>,
+    t41: Single<u32>,
+    t42: Single<u32>,
+    t43: Single<u32>,
+    t44: Single<u32>,
+    t45: Single<u32>,
+    t46: Single<u32>,
+    t47: Single<u32>,
+    t48: Single<u32>,
+    t49: Single<u32>,
+    t50: Single<u32>,
+    t51: Single<u32>,
+    t52: Single<u32>,
+    t53: Single<u32>,
+    t54: Single<u32>,
+    t55: Single<u32>,
+    t56: Single<u32>,
+    t57: Single<u32>,
+    t58: Single<u32>,
+    t59: Single<u32>,
+    t60: Single<u32>,
+    t61: Single<u32>,
+    t62: Single<u32>,
+    t63: Single<u32>,
+    t64: Single<u32>,
+    t65: Single<u32>,
+    t66: Single<u32>,
+    t67: Single<u32>,
+    t68: Single<u32>,
+    t69: Single<u32>,
+    t70: Single<u32>,
+    t71: Single<u32>,
+    t72: Single<u32>,
+    t73: Single<u32>,
+    t74: Single<u32>,
+    t75: Single<u32>,
+    t76: Single<u32>,
+    t77: Single<u32>,
+    t78: Single<u32>,
+    t79: Single<u32>,
+    t80: Single<u32>,
+    t81: Single<u32>,
+    t82: Single<u32>,
+    t83: Single<u32>,
+    t84: Single<u32>,
+    t85: Single<u32>,
+    t86: Single<u32>,
+    t87: Single<u32>,
+    t88: Single<u32>,
+    t89: Single<u32>,
+    t90: Single<u32>,
+    t91: Single<u32>,
+    t92: Single<u32>,
+    t93: Single<u32>,
+    t94: Single<u32>,
+    t95: Single<u32>,
+    t96: Single<u32>,
+    t97: Single<u32>,
+    t98: Single<u32>,
+    t99: Single<u32>,
+    t100: Single<u32>,
+    t101: Single<u32>,
+    t102: Single<u32>,
+    t103: Single<u32>,
+    t104: Single<u32>,
+    t105: Single<u32>,
+    t106: Single<u32>,
+    t107: Single<u32>,
+    t108: Single<u32>,
+    t109: Single<u32>,
+    t110: Single<u32>,
+    t111: Single<u32>,
+    t112: Single<u32>,
+    t113: Single<u32>,
+    t114: Single<u32>,
+    t115: Single<u32>,
+    t116: Single<u32>,
+    t117: Single<u32>,
+    t118: Single<u32>,
+    t119: Single<u32>,
+    t120: Single<u32>,
+    t121: Single<u32>,
+    t122: Single<u32>,
+    t123: Single<u32>,
+    t124: Single<u32>,
+    t125: Single<u32>,
+    t126: Single<u32>,
+    t127: Single<u32>,
+    t128: Single<u32>,
+    t129: Single<u32>,
+    t130: Single<u32>,
+    t131: Single<u32>,
+    t132: Single<u32>,
+    t133: Single<u32>,
+    t134: Single<u32>,
+    t135: Single<u32>,
+    t136: Single<u32>,
+    t137: Single<u32>,
+    t138: Single<u32>,
+    t139: Single<u32>,
+    t140: Single<u32>,
+    t141: Single<u32>,
+    t142: Single<u32>,
+    t143: Single<u32>,
+    t144: Single<u32>,
+    t145: Single<u32>,
+    t146: Single<u32>,
+    t147: Single<u32>,
+    t148: Single<u32>,
+    t149: Single<u32>,
+    t150: Single<u32>,
+    t151: Single<u32>,
+    t152: Single<u32>,
+    t153: Single<u32>,
+    t154: Single<u32>,
+    t155: Single<u32>,
+    t156: Single<u32>,
+    t157: Single<u32>,
+    t158: Single<u32>,
+    t159: Single<u32>,
+    t160: Single<u32>,
+    t161: Single<u32>,
+    t162: Single<u32>,
+    t163: Single<u32>,
+    t164: Single<u32>,
+    t165: Single<u32>,
+    t166: Single<u32>,
+    t167: Single<u32>,
+    t168: Single<u32>,
+    t169: Single<u32>,
+    t170: Single<u32>,
+    t171: Single<u32>,
+    t172: Single<u32>,
+    t173: Single<u32>,
+    t174: Single<u32>,
+    t175: Single<u32>,
+    t176: Single<u32>,
+    t177: Single<u32>,
+    t178: Single<u32>,
+    t179: Single<u32>,
+    t180: Single<u32>,
+    t181: Single<u32>,
+    t182: Single<u32>,
+    t183: Single<u32>,
+    t184: Single<u32>,
+    t185: Single<u32>,
+    t186: Single<u32>,
+    t187: Single<u32>,
+    t188: Single<u32>,
+    t189: Single<u32>,
+    t190: Single<u32>,
+    t191: Single<u32>,
+    t192: Single<u32>,
+    t193: Single<u32>,
+    t194: Single<u32>,
+    t195: Single<u32>,
+    t196: Single<u32>,
+    t197: Single<u32>,
+    t198: Single<u32>,
+    t199: Single<u32>,
+    t200: Single<u32>,
+    t201: Single<u32>,
+    t202: Single<u32>,
+    t203: Single<u32>,
+    t204: Single<u32>,
+    t205: Single<u32>,
+    t206: Single<u32>,
+    t207: Single<u32>,
+    t208: Single<u32>,
+    t209: Single<u32>,
+    t210: Single<u32>,
+    t211: Single<u32>,
+    t212: Single<u32>,
+    t213: Single<u32>,
+    t214: Single<u32>,
+    t215: Single<u32>,
+    t216: Single<u32>,
+    t217: Single<u32>,
+    t218: Single<u32>,
+    t219: Single<u32>,
+    t220: Single<u32>,
+    t221: Single<u32>,
+    t222: Single<u32>,
+    t223: Single<u32>,
+    t224: Single<u32>,
+    t225: Single<u32>,
+    t226: Single<u32>,
+    t227: Single<u32>,
+    t228: Single<u32>,
+    t229: Single<u32>,
+    t230: Single<u32>,
+    t231: Single<u32>,
+    t232: Single<u32>,
+    t233: Single<u32>,
+    t234: Single<u32>,
+    t235: Single<u32>,
+    t236: Single<u32>,
+    t237: Single<u32>,
+    t238: Single<u32>,
+    t239: Single<u32>,
+    t240: Single<u32>,
+    t241: Single<u32>,
+    t242: Single<u32>,
+    t243: Single<u32>,
+    t244: Single<u32>,
+    t245: Single<u32>,
+    t246: Single<u32>,
+    t247: Single<u32>,
+    t248: Single<u32>,
+    t249: Single<u32>,
+    t250: Single<u32>,
+    t251: Single<u32>,
+    t252: Single<u32>,
+    t253: Single<u32>,
+    t254: Single<u32>,
+    t255: Single<u32>,
+    t256: Single<u32>,
+    t257: Single<u32>,
+    t258: Single<u32>,
+    t259: Single<u32>,
+    t260: Single<u32>,
+    t261: Single<u32>,
+    t262: Single<u32>,
+    t263: Single<u32>,
+    t264: Single<u32>,
+    t265: Single<u32>,
+    t266: Single<u32>,
+    t267: Single<u32>,
+    t268: Single<u32>,
+    t269: Single<u32>,
+    t270: Single<u32>,
+    t271: Single<u32>,
+    t272: Single<u32>,
+    t273: Single<u32>,
+    t274: Single<u32>,
+    t275: Single<u32>,
+    t276: Single<u32>,
+    t277: Single<u32>,
+    t278: Single<u32>,
+    t279: Single<u32>,
+    t280: Single<u32>,
+    t281: Single<u32>,
+    t282: Single<u32>,
+    t283: Single<u32>,
+    t284: Single<u32>,
+    t285: Single<u32>,
+    t286: Single<u32>,
+    t287: Single<u32>,
+    t288: Single<u32>,
+    t289: Single<u32>,
+    t290: Single<u32>,
+    t291: Single<u32>,
+    t292: Single<u32>,
+    t293: Single<u32>,
+    t294: Single<u32>,
+    t295: Single<u32>,
+    t296: Single<u32>,
+    t297: Single<u32>,
+    t298: Single<u32>,
+    tally: Tally,
+}
+
+fn log_version(dir: &str) -> u8 {
+    fs::read(format!("{dir}accounts.db")).unwrap()[0]
+}
+
+#[test]
+fn more_than_254_tables() {
+    let dir = "/tmp/wide1/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = ManyAccounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(log_version(dir), 4);
+    db.accounts.insert(1, "parth".into()).unwrap();
+    db.h0.x0.t0.insert(2).unwrap();
+    db.h2.x5.t4.insert(256).unwrap();
+    db.h2.x9.t9.insert(301).unwrap();
+    db.last.insert("last".into()).unwrap();
+
+    let ids = db.stats().unwrap().tables.into_keys().collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2, 256, 301, 302]);
+    drop(db);
+
+    let mut db = ManyAccounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.h0.x0.t0.get(), Some(&2));
+    assert_eq!(db.h2.x5.t4.get(), Some(&256));
+    assert_eq!(db.h2.x9.t9.get(), Some(&301));
+    assert_eq!(db.last.get().unwrap(), "last");
+
+    db.h2.x9.t9.insert(302).unwrap();
+    db.compact_log().unwrap();
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.tables[&301].entries, 1);
+    assert_eq!(stats.tables[&302].entries, 1);
+    drop(db);
+
+    let db = ManyAccounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get()[&1], "parth");
+    assert_eq!(db.h2.x9.t9.get(), Some(&302));
+    assert_eq!(log_version(dir), 4);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn narrow_logs_are_upgraded() {
+    let dir = "/tmp/wide2/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = Accounts::init(Config::in_folder(dir)).unwrap();
+    db.accounts.insert(1, "travis".into()).unwrap();
+    drop(db);
+    assert_eq!(log_version(dir), 2);
+
+    let mut db = ManyAccounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(log_version(dir), 4);
+    assert_eq!(db.accounts.get()[&1], "travis");
+    db.last.insert("wide".into()).unwrap();
+    drop(db);
+
+    let db = ManyAccounts::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.accounts.get()[&1], "travis");
+    assert_eq!(db.last.get().unwrap(), "wide");
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn wide_ids_need_a_wide_log() {
+    let dir = "/tmp/wide3/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut config = Config::in_folder(dir);
+    config.schema_name = Some("logger".into());
+    let log = Logger::init(config).unwrap();
+    log.write(254, vec![1]).unwrap();
+    assert!(matches!(log.write(255, vec![1]), Err(DbError::WideTableId { id: 255 })));
+    drop(log);
+
+    let mut config = Config::in_folder(dir);
+    config.schema_name = Some("wide_logger".into());
+    config.wide_table_ids = true;
+    let log = Logger::init(config).unwrap();
+    log.write(255, vec![1]).unwrap();
+    log.write(u16::MAX, vec![2]).unwrap();
+    let bytes = log.get_bytes().unwrap();
+    let entries = log.get_entries(&bytes).unwrap();
+    let entries: Vec<_> = entries.iter().map(|e| (e.table_id, e.bytes)).collect();
+    assert_eq!(entries, [(255, &[1][..]), (u16::MAX, &[2][..])]);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn flat_schema_past_the_narrow_limit() {
+    let dir = "/tmp/wide4/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = Flat::init(Config::in_folder(dir)).unwrap();
+    db.t0.insert(1).unwrap();
+    db.t254.insert(255).unwrap();
+    db.tally.set(300).unwrap();
+    let ids = db.stats().unwrap().tables.into_keys().collect::<Vec<_>>();
+    assert_eq!(ids, [1, 255, 300]);
+    drop(db);
+
+    let mut db = Flat::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.t0.get(), Some(&1));
+    assert_eq!(db.t254.get(), Some(&255));
+    assert_eq!(db.tally.count, 300);
+    db.compact_log().unwrap();
+    drop(db);
+
+    let db = Flat::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.tally.count, 300);
+
+    drop(fs::remove_dir_all(dir));
+}
+
+#[test]
+fn snapshots_past_the_narrow_limit() {
+    let dir = "/tmp/wide5/";
+    drop(fs::remove_dir_all(dir));
+
+    let mut db = Flat::init(Config::in_folder(dir)).unwrap();
+    db.t254.insert(255).unwrap();
+    db.tally.set(1).unwrap();
+    let snapshot = db.snapshot().unwrap();
+    assert_eq!(snapshot.tally.count, 1);
+    assert_eq!(snapshot.t254.get(), Some(&255));
+
+    db.tally.set(2).unwrap();
+    snapshot.compact_log().unwrap();
+    assert_eq!(db.stats().unwrap().tables[&300].entries, 2);
+    drop(snapshot);
+    drop(db);
+
+    let db = Flat::init(Config::in_folder(dir)).unwrap();
+    assert_eq!(db.tally.count, 2);
+    assert_eq!(db.t254.get(), Some(&255));
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -0,0 +1,82 @@
+use db_rs::{Config, Db, List, LookupList, LookupSet, LookupTable, Single};
+use db_rs_derive::Schema;
+use std::collections::HashSet;
+use std::fs;
+
+#[derive(Schema)]
+struct WriteAhead {
+    list: List<u8>,
+    lookup: LookupTable<u8, u8>,
+    lookup_list: LookupList<u8, u8>,
+    lookup_set: LookupSet<u8, u8>,
+    single: Single<u8>,
+}
+
+fn populate(db: &mut WriteAhead) {
+    db.list.push(1).unwrap();
+    db.lookup.insert(1, 1).unwrap();
+    db.lookup_list.push(1, 1).unwrap();
+    db.lookup_set.insert(1, 1).unwrap();
+    db.single.insert(1).unwrap();
+}
+
+fn assert_unchanged(db: &WriteAhead) {
+    assert_eq!(db.list.get(), [1]);
+    assert_eq!(db.lookup.get().len(), 1);
+    assert_eq!(db.lookup.get()[&1], 1);
+    assert_eq!(db.lookup_list.get().len(), 1);
+    assert_eq!(db.lookup_list.get()[&1], [1]);
+    assert_eq!(db.lookup_set.get().len(), 1);
+    assert_eq!(db.lookup_set.get()[&1], HashSet::from([1]));
+    assert_eq!(db.single.get(), Some(&1));
+}
+
+#[test]
+fn failed_writes_leave_tables_unchanged() {
+    let dir = "/tmp/write_ahead1/";
+    drop(fs::remove_dir_all(dir));
+    let mut db = WriteAhead::init(Config::in_folder(dir)).unwrap();
+    populate(&mut db);
+    drop(db);
+
+    // the log file is opened read only, so every write to it fails
+    let mut config = Config::in_folder(dir);
+    config.read_only = true;
+    let mut db = WriteAhead::init(config).unwrap();
+    let size = db.log_stats().unwrap().log_size;
+
+    assert!(db.list.push(2).is_err());
+    assert!(db.list.pop().is_err());
+    assert!(db.list.remove(0).is_err());
+    assert!(db.list.clear().is_err());
+    assert!(db.lookup.insert(2, 2).is_err());
+    assert!(db.lookup.remove(&1).is_err());
+    assert!(db.lookup.clear().is_err());
+    assert!(db.lookup_list.push(1, 2).is_err());
+    assert!(db.lookup_list.create_key(2).is_err());
+    assert!(db.lookup_list.remove(&1, 0).is_err());
+    assert!(db.lookup_list.clear_key(&1).is_err());
+    assert!(db.lookup_list.clear().is_err());
+    assert!(db.lookup_set.insert(1, 2).is_err());
+    assert!(db.lookup_set.create_key(2).is_err());
+    assert!(db.lookup_set.remove(&1, &1).is_err());
+    assert!(db.lookup_set.clear_key(&1).is_err());
+    assert!(db.lookup_set.clear().is_err());
+    assert!(db.single.insert(2).is_err());
+    assert!(db.single.clear().is_err());
+
+    assert_unchanged(&db);
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.log.writes_since_compaction, 0);
+    assert_eq!(stats.log.log_size, size);
+    assert!(stats
+        .tables
+        .values()
+        .all(|table| table.entries == 1 && table.writes_since_compaction == 0));
+
+    drop(db);
+    let db = WriteAhead::init(Config::in_folder(dir)).unwrap();
+    assert_unchanged(&db);
+
+    drop(fs::remove_dir_all(dir));
+}
@@ -1,84 +1,737 @@
-use db_rs::TableId;
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
 use syn::parse_macro_input;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::*;
 
-#[proc_macro_derive(Schema)]
+#[proc_macro_derive(Schema, attributes(schema, table))]
 pub fn schema(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-    let tables = match data {
-        Data::Struct(DataStruct { struct_token: _, fields, semi_token: _ }) => match fields {
-            Fields::Named(FieldsNamed { brace_token: _, named: tables }) => tables,
-            _ => panic!("db fields must all be named"),
-        },
-        _ => panic!("db schema must be a struct"),
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let DeriveInput { ident, data, attrs, mut generics, .. } = input;
+
+    if let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("table")) {
+        return Err(Error::new_spanned(attr, "`#[table(...)]` belongs on a table of the schema"));
+    }
+    let args = SchemaArgs::from_attrs(&attrs)?;
+
+    let fields = match data {
+        Data::Struct(DataStruct { fields: Fields::Named(fields), .. }) => fields.named,
+        Data::Struct(DataStruct { fields: Fields::Unnamed(fields), .. }) => {
+            return Err(Error::new_spanned(
+                fields,
+                "the tables of a schema must be named fields, tuple structs aren't supported",
+            ))
+        }
+        Data::Struct(DataStruct { fields: Fields::Unit, .. }) => Punctuated::new(),
+        Data::Enum(DataEnum { enum_token, .. }) => {
+            return Err(Error::new_spanned(enum_token, "a schema must be a struct of tables"))
+        }
+        Data::Union(DataUnion { union_token, .. }) => {
+            return Err(Error::new_spanned(union_token, "a schema must be a struct of tables"))
+        }
+    };
+
+    // skipped fields aren't persisted, and don't get a table id
+    let mut tables = vec![];
+    let mut skipped = vec![];
+    for field in fields {
+        let kind = field_kind(&field)?;
+        if kind != FieldKind::Table {
+            if let Some(attr) = field.attrs.iter().find(|attr| attr.path.is_ident("table")) {
+                return Err(Error::new_spanned(
+                    attr,
+                    match kind {
+                        FieldKind::Nested => {
+                            "nested schemas aren't tables, `#[table(...)]` belongs on their own tables"
+                        }
+                        _ => "skipped fields aren't tables",
+                    },
+                ));
+            }
+        }
+        match kind {
+            FieldKind::Skipped => skipped.push(field),
+            kind => tables.push((field, kind)),
+        }
+    }
+
+    if tables.is_empty() {
+        return Err(Error::new_spanned(
+            &ident,
+            "a schema needs at least one table, such as `Single<T>` or `LookupTable<K, V>`",
+        ));
+    }
+
+    // ids start at 1, narrow ids end at 254 as 255 marks a wide id
+    let max_tables = if args.wide_table_ids { u16::MAX as usize } else { u8::MAX as usize - 1 };
+    let too_many = if args.wide_table_ids {
+        format!("too many tables, a schema can have at most {max_tables}")
+    } else {
+        format!(
+            "too many tables, a schema can have at most {max_tables}, \
+             or {} with `#[schema(wide_table_ids)]`",
+            u16::MAX
+        )
     };
+    if let Some((table, _)) = tables.get(max_tables) {
+        return Err(Error::new_spanned(table, &too_many));
+    }
 
-    let types = tables.iter().map(|table| &table.ty);
+    let generic = !generics.params.is_empty();
 
-    let idents: &Vec<&Ident> = &tables
+    let mut inits = vec![];
+    let mut replays = vec![];
+    let mut names = vec![];
+    let mut descriptions = vec![];
+    let mut writes = vec![];
+    let mut snapshots = vec![];
+    let mut replacements = vec![];
+    let mut offsets = vec![];
+    let mut infos = vec![];
+    let mut visits = vec![];
+    let mut reflect_predicates: Vec<WherePredicate> = vec![];
+    // the offset of a table from the first id of the schema is its index until a nested schema,
+    // whose size is only known to the compiler, precedes it. From there each table gets a const
+    // that adds the size of the one before it to that table's offset, so the generated code
+    // stays linear in the number of tables.
+    let mut previous: Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)> = None;
+    let mut after_nested = false;
+    for (i, (table, kind)) in tables.iter().enumerate() {
+        let ident = table.ident.as_ref().unwrap();
+        let ty = &table.ty;
+        let (offset, is_const) = match (&previous, after_nested) {
+            (Some((offset, count)), true) => {
+                let name = format_ident!("__DB_RS_OFFSET_{}", i);
+                offsets.push(quote! { const #name: usize = #offset + #count; });
+                (quote! { Self::#name }, true)
+            }
+            _ => {
+                let index = proc_macro2::Literal::usize_unsuffixed(i);
+                (quote! { #index }, false)
+            }
+        };
+        let count = match kind {
+            FieldKind::Nested => {
+                after_nested = true;
+                quote_spanned! { ty.span() => <#ty as db_rs::Tables>::TABLE_COUNT }
+            }
+            _ => quote! { 1 },
+        };
+        previous = Some((offset.clone(), count));
+
+        // ids are relative to the first id of the schema, which is 1 unless it's nested
+        let type_name = type_name(ty);
+        let (id, own_id) = match (i, is_const) {
+            (0, _) => (quote! { first_id }, quote! { 1 }),
+            (_, false) => (quote! { first_id + #offset }, quote! { 1 + #offset }),
+            (_, true) => (
+                quote! { first_id + #offset as db_rs::TableId },
+                quote! { 1 + #offset as db_rs::TableId },
+            ),
+        };
+
+        if *kind == FieldKind::Nested {
+            let as_tables = quote_spanned! { ty.span() => <#ty as db_rs::Tables> };
+            if generic {
+                let predicates = &mut generics.make_where_clause().predicates;
+                predicates.push(parse_quote_spanned! { ty.span() => #ty: db_rs::Tables });
+            }
+
+            inits.push(quote! { #as_tables::init_tables(#id, logger.clone()) });
+            replays.push(quote! {
+                let first = #id;
+                if id >= first && ((id - first) as usize) < #as_tables::TABLE_COUNT {
+                    return #as_tables::replay_entry(&mut self.#ident, first, replay, entry);
+                }
+            });
+            names.push(quote! { #as_tables::table_names(#id, names); });
+            descriptions.push(quote! {
+                #as_tables::describe_tables(
+                    #id,
+                    &format!("{prefix}{}.", stringify!(#ident)),
+                    tables,
+                );
+            });
+            writes.push(quote! { #as_tables::write_tables(&self.#ident, out)?; });
+            replacements
+                .push(quote! { #as_tables::replace_tables(&mut self.#ident, from.#ident); });
+            snapshots.push(quote! {
+                #as_tables::snapshot_tables(&self.#ident, #id, logger.clone())?
+            });
+
+            let as_reflect = quote_spanned! { ty.span() => <#ty as db_rs::reflect::Reflect> };
+            infos.push(quote! {
+                db_rs::reflect::TableInfo {
+                    name: stringify!(#ident),
+                    id: #own_id,
+                    type_name: #type_name,
+                    nested: #as_reflect::TABLES,
+                }
+            });
+            visits.push(quote! { #as_reflect::visit_tables_from(&self.#ident, #id, visit); });
+            reflect_predicates
+                .push(parse_quote_spanned! { ty.span() => #ty: db_rs::reflect::Reflect });
+            continue;
+        }
+
+        let table_args = TableArgs::from_attrs(&table.attrs)?;
+        let handler = table_args.handler(&quote! { &mut self.#ident }, ty);
+
+        // the tables of a generic schema are only known to be tables once it's instantiated
+        if generic {
+            let predicates = &mut generics.make_where_clause().predicates;
+            predicates.push(parse_quote_spanned! { ty.span() => #ty: db_rs::table::Table });
+            if let Some(current) = table_args.upgrade.last() {
+                predicates.push(parse_quote_spanned! { ty.span() =>
+                    #ty: db_rs::table::Upgrade<Value = #current>
+                });
+            }
+        }
+
+        // spanned to the field's type, so a type that isn't a table is reported there
+        let as_table = quote_spanned! { ty.span() => <#ty as db_rs::table::Table> };
+        inits.push(quote! { #as_table::init(#id, logger.clone()) });
+        replays.push(quote! {
+            if id == #id {
+                return replay.entry(id, stringify!(#ident), entry.offset, || { #handler });
+            }
+        });
+        names.push(quote! { names.push((#id, stringify!(#ident))); });
+        descriptions.push(quote! {
+            tables.push(db_rs::descriptor::TableDescriptor {
+                name: format!("{prefix}{}", stringify!(#ident)),
+                id: #id,
+                kind: #as_table::kind(),
+            });
+        });
+        writes.push(quote! { #as_table::write_compact_repr(&self.#ident, out)?; });
+        replacements.push(quote! { self.#ident = from.#ident; });
+        snapshots.push(quote! { #as_table::snapshot(&self.#ident, #id, logger.clone())? });
+
+        infos.push(quote! {
+            db_rs::reflect::TableInfo {
+                name: stringify!(#ident),
+                id: #own_id,
+                type_name: #type_name,
+                nested: &[],
+            }
+        });
+        let as_view =
+            quote_spanned! { ty.span() => &self.#ident as &dyn db_rs::reflect::TableView };
+        visits.push(quote! { visit(#id, &Self::TABLES[#i], #as_view); });
+        reflect_predicates
+            .push(parse_quote_spanned! { ty.span() => #ty: db_rs::reflect::TableView });
+    }
+
+    let skipped_idents: &Vec<&Ident> = &skipped
         .iter()
-        .map(|table| table.ident.as_ref().unwrap())
+        .map(|field| field.ident.as_ref().unwrap())
         .collect();
-
-    let last = &tables
+    let defaults: &Vec<_> = &skipped
         .iter()
-        .map(|table| table.ident.as_ref().unwrap())
-        .next_back()
-        .unwrap_or_else(|| panic!("No tables found!"));
-
-    let max_tables = TableId::MAX - 1 as TableId;
-    if idents.len() > max_tables as usize {
-        panic!(
-            "Too many tables found, the maximum is: {max_tables}. Please file an issue with db-rs."
-        );
-    }
+        .map(|field| {
+            let ty = &field.ty;
+            if generic {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote_spanned! { ty.span() => #ty: Default });
+            }
+            quote_spanned! { ty.span() => <#ty as Default>::default() }
+        })
+        .collect();
 
-    let ids: Vec<u8> = (1..(idents.len() + 1) as u8).collect();
+    let idents: &Vec<&Ident> = &tables
+        .iter()
+        .map(|(table, _)| table.ident.as_ref().unwrap())
+        .collect();
+    let logger = match &tables[tables.len() - 1] {
+        (table, FieldKind::Nested) => {
+            let (ident, ty) = (&table.ident, &table.ty);
+            quote_spanned! { ty.span() => <#ty as db_rs::Tables>::tables_logger(&self.#ident) }
+        }
+        (table, _) => {
+            let (ident, ty) = (&table.ident, &table.ty);
+            quote_spanned! { ty.span() => &self.#ident.logger }
+        }
+    };
 
-    let output = quote! {
+    let schema_name = match args.name {
+        Some(name) => quote! { #name },
+        None => quote! { stringify!(#ident) },
+    };
 
-        impl db_rs::Db for #ident {
-            fn init(mut config: db_rs::Config) -> db_rs::DbResult<Self> {
-                use db_rs::table::Table;
+    let (migration, log_exists) = match args.migrate_from {
+        Some((from, with)) => (
+            quote! {
+                db_rs::migration::migrate::<#from, Self>(&config, #with)?;
+            },
+            quote! {
+                fn log_exists(config: &db_rs::Config) -> db_rs::DbResult<bool> {
+                    Ok(db_rs::migration::own_log_exists::<Self>(config)?
+                        || <#from as db_rs::Db>::log_exists(config)?)
+                }
+            },
+        ),
+        None => (quote! {}, quote! {}),
+    };
 
-                let schema_name = stringify!(#ident);
-                config.schema_name = Some(schema_name.to_string());
-                let mut log = db_rs::Logger::init(config)?;
-                let log_data = log.get_bytes()?;
-                let log_entries = log.get_entries(&log_data)?;
+    let wide_table_ids = match args.wide_table_ids {
+        true => quote! { config.wide_table_ids = true; },
+        false => quote! {},
+    };
+    let describe = match args.describe {
+        true => quote! {
+            config.schema_descriptor = Some(db_rs::descriptor::SchemaDescriptor::of::<Self>());
+        },
+        false => quote! {},
+    };
+    // existing logs get what the schema asks of their metadata when they're compacted
+    let upgrade_metadata = match args.wide_table_ids || args.describe {
+        true => quote! {
+            if db.get_logger().metadata_outdated()? {
+                db.get_logger().compact_log(|out| db.write_compact_repr(out))?;
+            }
+        },
+        false => quote! {},
+    };
 
-                #( let mut #idents = <#types>::init(#ids, log.clone()); )*
+    let reflect = match args.reflect {
+        true => {
+            let mut generics = generics.clone();
+            if generic {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .extend(reflect_predicates);
+            }
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+            quote! {
+                impl #impl_generics db_rs::reflect::Reflect for #ident #ty_generics #where_clause {
+                    const TABLES: &'static [db_rs::reflect::TableInfo] = &[ #( #infos, )* ];
 
-                for entry in log_entries {
-                    match entry.table_id {
-                        #( #ids => #idents.handle_event(entry.bytes)?, )*
-                        _ => todo!()
+                    fn visit_tables_from(
+                        &self, first_id: db_rs::TableId,
+                        visit: &mut dyn FnMut(
+                            db_rs::TableId,
+                            &'static db_rs::reflect::TableInfo,
+                            &dyn db_rs::reflect::TableView,
+                        ),
+                    ) {
+                        #( #visits )*
                     }
                 }
+            }
+        }
+        false => quote! {},
+    };
+
+    let table_count = match previous {
+        Some((offset, count)) if after_nested => quote! { #offset + #count },
+        _ => {
+            let count = proc_macro2::Literal::usize_unsuffixed(tables.len());
+            quote! { #count }
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let offsets = match offsets.is_empty() {
+        true => quote! {},
+        false => quote! {
+            #[doc(hidden)]
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #( #offsets )*
+            }
+        },
+    };
+
+    let output = quote! {
+        #reflect
+        #offsets
+
+        impl #impl_generics db_rs::Tables for #ident #ty_generics #where_clause {
+            const TABLE_COUNT: usize = #table_count;
+
+            fn init_tables(first_id: db_rs::TableId, logger: db_rs::Logger) -> Self {
+                Self {
+                    #( #idents: #inits, )*
+                    #( #skipped_idents: #defaults, )*
+                }
+            }
+
+            fn replay_entry(
+                &mut self, first_id: db_rs::TableId, replay: &mut db_rs::logger::Replay,
+                entry: &db_rs::logger::LogFormat,
+            ) -> db_rs::DbResult<()> {
+                let id = entry.table_id;
+                #( #replays )*
+                Err(db_rs::DbError::UnknownTable { table_id: id, offset: entry.offset })
+            }
+
+            fn table_names(
+                first_id: db_rs::TableId, names: &mut Vec<(db_rs::TableId, &'static str)>,
+            ) {
+                #( #names )*
+            }
 
+            fn describe_tables(
+                first_id: db_rs::TableId, prefix: &str,
+                tables: &mut Vec<db_rs::descriptor::TableDescriptor>,
+            ) {
+                #( #descriptions )*
+            }
+
+            fn replace_tables(&mut self, from: Self) {
+                #( #replacements )*
+            }
+
+            fn write_tables(&self, out: &mut dyn std::io::Write) -> db_rs::DbResult<()> {
+                #( #writes )*
+                Ok(())
+            }
+
+            fn snapshot_tables(
+                &self, first_id: db_rs::TableId, logger: db_rs::Logger,
+            ) -> db_rs::DbResult<Self> {
                 Ok(
                     Self {
-                        #( #idents, )*
+                        #( #idents: #snapshots, )*
+                        #( #skipped_idents: #defaults, )*
                     }
                 )
             }
 
-            fn compact_log(&mut self) -> db_rs::DbResult<()> {
-                use db_rs::table::Table;
+            fn tables_logger(&self) -> &db_rs::Logger {
+                #logger
+            }
+        }
+
+        impl #impl_generics db_rs::Db for #ident #ty_generics #where_clause {
+            fn schema_name() -> &'static str {
+                #schema_name
+            }
+
+            #log_exists
+
+            fn init(mut config: db_rs::Config) -> db_rs::DbResult<Self> {
+                config.schema_name = Some(Self::schema_name().to_string());
+                #wide_table_ids
+                #describe
+                #migration
+                let log = db_rs::Logger::init(config)?;
+                log.set_compacter(<Self as db_rs::Db>::compact_from_log)?;
+                let db = Self::load(log)?;
+                db.get_logger().measure_live_data(|out| db.write_compact_repr(out))?;
+                #upgrade_metadata
+                Ok(db)
+            }
+
+            fn load(log: db_rs::Logger) -> db_rs::DbResult<Self> {
+                const {
+                    assert!(
+                        <Self as db_rs::Tables>::TABLE_COUNT <= #max_tables,
+                        #too_many
+                    )
+                };
+
+                let mut replay = db_rs::logger::Replay::start();
+                let log_data = log.get_bytes()?;
+                let log_entries = log.get_entries(&log_data)?;
+
+                let mut db = <Self as db_rs::Tables>::init_tables(1, log.clone());
+                for entry in &log_entries {
+                    <Self as db_rs::Tables>::replay_entry(&mut db, 1, &mut replay, entry)?;
+                }
+
+                let mut names = vec![];
+                <Self as db_rs::Tables>::table_names(1, &mut names);
+                replay.finish(&names);
 
-                let mut data = vec![];
-                #( data.append(&mut self.#idents.compact_repr()?);)*
-                self.get_logger().compact_log(data)?;
+                Ok(db)
+            }
+
+            fn write_compact_repr(&self, out: &mut dyn std::io::Write) -> db_rs::DbResult<()> {
+                <Self as db_rs::Tables>::write_tables(self, out)
+            }
+
+            // skipped fields aren't in the log, so they're kept as they are
+            fn recover(&mut self) -> db_rs::DbResult<()> {
+                let logger = self.get_logger().clone();
+                logger.recover()?;
+                let loaded = Self::load(logger)?;
+                <Self as db_rs::Tables>::replace_tables(self, loaded);
                 Ok(())
             }
 
+            fn snapshot(&self) -> db_rs::DbResult<db_rs::Snapshot<Self>> {
+                db_rs::Snapshot::new(self.get_logger(), |logger| {
+                    <Self as db_rs::Tables>::snapshot_tables(self, 1, logger.clone())
+                })
+            }
+
             fn get_logger(&self) -> &db_rs::Logger {
-                &self.#last.logger
+                <Self as db_rs::Tables>::tables_logger(self)
             }
         }
     };
-    output.into()
+    Ok(output)
+}
+
+/// `ty` as it would be written, `stringify!` spaces out every token.
+fn type_name(ty: &Type) -> String {
+    let mut name = quote!(#ty).to_string();
+    for (spaced, written) in [
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        (" ;", ";"),
+        (" ::", "::"),
+        (":: ", "::"),
+        ("& ", "&"),
+    ] {
+        name = name.replace(spaced, written);
+    }
+    name
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Table,
+    /// `#[schema(nested)]`, a schema whose tables are mounted in this one
+    Nested,
+    /// `#[schema(skip)]`, not persisted and built with `Default`
+    Skipped,
+}
+
+/// What `field` is, marked with `#[schema(skip)]` or `#[schema(nested)]`, the options fields take.
+fn field_kind(field: &Field) -> Result<FieldKind> {
+    let mut kind = FieldKind::Table;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("schema"))
+    {
+        let options = attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated);
+        let options = options.map_err(|_| {
+            Error::new_spanned(
+                attr,
+                "fields only take `#[schema(skip)]` or `#[schema(nested)]`, \
+                 other options belong on the struct",
+            )
+        })?;
+        for option in options {
+            let option_kind = match option.to_string().as_str() {
+                "skip" => FieldKind::Skipped,
+                "nested" => FieldKind::Nested,
+                _ => {
+                    return Err(Error::new(
+                        option.span(),
+                        format!(
+                            "`{option}` isn't an option for fields, \
+                             which take `#[schema(skip)]` or `#[schema(nested)]`"
+                        ),
+                    ))
+                }
+            };
+            if kind != FieldKind::Table && kind != option_kind {
+                return Err(Error::new(option.span(), "a field can't be both skipped and nested"));
+            }
+            kind = option_kind;
+        }
+    }
+    Ok(kind)
+}
+
+/// The options of `#[schema(...)]`.
+#[derive(Default)]
+struct SchemaArgs {
+    /// overrides the name of the struct as the name of the schema, and its log
+    name: Option<LitStr>,
+    /// the schema to migrate from and the function that does it
+    migrate_from: Option<(Type, Path)>,
+    /// whether the log has room for more than 254 tables
+    wide_table_ids: bool,
+    /// whether to implement `Reflect`
+    reflect: bool,
+    /// whether to store a description of the schema in its log
+    describe: bool,
+}
+
+impl SchemaArgs {
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut args = Self::default();
+        let mut migrate_from = None;
+        let mut with = None;
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("schema")) {
+            let parsed =
+                attr.parse_args_with(Punctuated::<SchemaArg, Token![,]>::parse_terminated)?;
+            for arg in parsed {
+                match arg {
+                    SchemaArg::MigrateFrom(from) => migrate_from = Some(from),
+                    SchemaArg::With(path) => with = Some(path),
+                    SchemaArg::WideTableIds => args.wide_table_ids = true,
+                    SchemaArg::Reflect => args.reflect = true,
+                    SchemaArg::Describe => args.describe = true,
+                    SchemaArg::Name(name) => {
+                        let value = name.value();
+                        if value.is_empty() || value.contains(['/', '\\']) {
+                            return Err(Error::new_spanned(
+                                name,
+                                "the schema name names its log file, and must be a file name",
+                            ));
+                        }
+                        args.name = Some(name);
+                    }
+                }
+            }
+
+            match (migrate_from.take(), with.take()) {
+                (Some(from), Some(with)) => args.migrate_from = Some((from, with)),
+                (None, None) => {}
+                _ => {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "migrate_from and with must be used together: \
+                         #[schema(migrate_from = OldSchema, with = migrate_fn)]",
+                    ))
+                }
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+enum SchemaArg {
+    Name(LitStr),
+    WideTableIds,
+    Reflect,
+    Describe,
+    MigrateFrom(Type),
+    With(Path),
+}
+
+impl Parse for SchemaArg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        if name == "skip" {
+            return Err(Error::new(name.span(), "`skip` belongs on a field that isn't a table"));
+        }
+        if name == "nested" {
+            return Err(Error::new(name.span(), "`nested` belongs on a field that's a schema"));
+        }
+        if name == "wide_table_ids" {
+            return Ok(SchemaArg::WideTableIds);
+        }
+        if name == "reflect" {
+            return Ok(SchemaArg::Reflect);
+        }
+        if name == "describe" {
+            return Ok(SchemaArg::Describe);
+        }
+        input.parse::<Token![=]>()?;
+        match name.to_string().as_str() {
+            "migrate_from" => Ok(SchemaArg::MigrateFrom(input.parse()?)),
+            "with" => Ok(SchemaArg::With(input.parse()?)),
+            "name" => Ok(SchemaArg::Name(input.parse()?)),
+            _ => Err(Error::new(
+                name.span(),
+                format!(
+                    "unknown schema option `{name}`, \
+                     expected `name`, `migrate_from`, `with`, `wide_table_ids`, `reflect` or `describe`"
+                ),
+            )),
+        }
+    }
+}
+
+/// The options of `#[table(...)]`.
+#[derive(Default)]
+struct TableArgs {
+    /// older value types, oldest first, followed by the current one
+    upgrade: Vec<Type>,
+}
+
+impl TableArgs {
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut args = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("table")) {
+            attr.parse_args_with(|input: ParseStream| {
+                let name: Ident = input.parse()?;
+                if name != "upgrade" {
+                    return Err(Error::new(
+                        name.span(),
+                        format!("unknown table option `{name}`, expected `upgrade`"),
+                    ));
+                }
+                input.parse::<Token![=]>()?;
+
+                let content;
+                bracketed!(content in input);
+                let versions = Punctuated::<Type, Token![->]>::parse_separated_nonempty(&content)?;
+                if versions.len() < 2 {
+                    return Err(Error::new_spanned(
+                        versions,
+                        "expected at least two versions: #[table(upgrade = [OldValue -> Value])]",
+                    ));
+                }
+                args.upgrade = versions.into_iter().collect();
+                Ok(())
+            })?;
+        }
+
+        Ok(args)
+    }
+
+    /// Code that replays `entry` into `table`, a `&mut` to the table. Entries that don't deserialize are tried
+    /// as each older version, newest first, and converted up through every version after it.
+    fn handler(&self, table: &proc_macro2::TokenStream, ty: &Type) -> proc_macro2::TokenStream {
+        let (current, olds) = match self.upgrade.split_last() {
+            Some(split) => split,
+            None => {
+                return quote_spanned! { ty.span() =>
+                    <#ty as db_rs::table::Table>::handle_event(#table, entry.bytes)
+                }
+            }
+        };
+
+        let fallbacks = olds.iter().enumerate().rev().map(|(i, old)| {
+            let upgraded = self.upgrade[i + 1..]
+                .iter()
+                .fold(quote! { old }, |value, version| quote! { <#version>::from(#value) });
+            quote! {
+                let result = match result {
+                    Err(db_rs::DbError::Bincode(err)) => {
+                        let upgraded = db_rs::table::Upgrade::handle_old_event(
+                            #table,
+                            entry.bytes,
+                            |old: #old| -> #current { #upgraded },
+                        );
+                        match upgraded {
+                            Err(db_rs::DbError::Bincode(_)) => Err(db_rs::DbError::Bincode(err)),
+                            upgraded => upgraded,
+                        }
+                    }
+                    result => result,
+                };
+            }
+        });
+
+        quote! {
+            let result = <#ty as db_rs::table::Table>::handle_event(#table, entry.bytes);
+            #( #fallbacks )*
+            result
+        }
+    }
 }